@@ -0,0 +1,9 @@
+include!("src/words.rs");
+
+fn main(){
+	let out_dir=std::env::var("OUT_DIR").unwrap();
+	hash_str::codegen::StaticTable::from_iter(WORDS)
+		.write_to_file(std::path::Path::new(&out_dir).join("words.rs"))
+		.unwrap();
+	println!("cargo::rerun-if-changed=src/words.rs");
+}
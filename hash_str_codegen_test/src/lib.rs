@@ -0,0 +1,23 @@
+//! Compiles the table generated by `hash_str::codegen` in build.rs,
+//! the same way a downstream crate would.
+include!("words.rs");
+
+pub mod words{
+	include!(concat!(env!("OUT_DIR"),"/words.rs"));
+}
+
+#[test]
+fn test_generated_table(){
+	use hash_str::{HashStr,SIZE_HASH};
+	assert_eq!(words::LEN,WORDS.len());
+	assert_eq!(words::iter().len(),WORDS.len());
+	for (i,&word) in WORDS.iter().enumerate(){
+		let hash_str=words::get(i).unwrap();
+		assert_eq!(hash_str.as_str(),word);
+		// hashed for the target by the build script
+		assert_eq!(hash_str,&*HashStr::anonymous(word.to_owned()));
+		assert_eq!(hash_str.as_hash_str_bytes().as_ptr().align_offset(SIZE_HASH),0);
+	}
+	assert!(words::iter().map(HashStr::as_str).eq(WORDS.iter().copied()));
+	assert_eq!(words::get(WORDS.len()),None);
+}
@@ -0,0 +1,2 @@
+/// Words written into the generated table, including ones which must be escaped.
+pub const WORDS:&[&str]=&["if","else","","quote\"","back\\slash","multi\nline","unicode ✓","hash_str"];
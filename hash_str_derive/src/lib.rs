@@ -0,0 +1,160 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input,Data,DeriveInput,Fields,LitStr};
+
+#[derive(Clone,Copy)]
+enum RenameRule{
+	Lower,
+	Upper,
+	Pascal,
+	Camel,
+	Snake,
+	ScreamingSnake,
+	Kebab,
+	ScreamingKebab,
+}
+
+impl RenameRule{
+	fn from_str(rule:&str)->Option<Self>{
+		Some(match rule{
+			"lowercase"=>RenameRule::Lower,
+			"UPPERCASE"=>RenameRule::Upper,
+			"PascalCase"=>RenameRule::Pascal,
+			"camelCase"=>RenameRule::Camel,
+			"snake_case"=>RenameRule::Snake,
+			"SCREAMING_SNAKE_CASE"=>RenameRule::ScreamingSnake,
+			"kebab-case"=>RenameRule::Kebab,
+			"SCREAMING-KEBAB-CASE"=>RenameRule::ScreamingKebab,
+			_=>return None,
+		})
+	}
+	/// Rename a PascalCase variant name.
+	fn apply(self,variant:&str)->String{
+		match self{
+			RenameRule::Lower=>variant.to_ascii_lowercase(),
+			RenameRule::Upper=>variant.to_ascii_uppercase(),
+			RenameRule::Pascal=>variant.to_owned(),
+			RenameRule::Camel=>{
+				let mut chars=variant.chars();
+				match chars.next(){
+					Some(first)=>first.to_lowercase().chain(chars).collect(),
+					None=>String::new(),
+				}
+			},
+			RenameRule::Snake=>{
+				let mut snake=String::new();
+				for (i,ch) in variant.char_indices(){
+					if 0<i&&ch.is_uppercase(){
+						snake.push('_');
+					}
+					snake.extend(ch.to_lowercase());
+				}
+				snake
+			},
+			RenameRule::ScreamingSnake=>RenameRule::Snake.apply(variant).to_ascii_uppercase(),
+			RenameRule::Kebab=>RenameRule::Snake.apply(variant).replace('_',"-"),
+			RenameRule::ScreamingKebab=>RenameRule::ScreamingSnake.apply(variant).replace('_',"-"),
+		}
+	}
+}
+
+/// Parse `#[hash_str(key="value")]` attributes, calling `f` with each key and value.
+fn parse_attrs(attrs:&[syn::Attribute],mut f:impl FnMut(&syn::Ident,LitStr)->syn::Result<()>)->syn::Result<()>{
+	for attr in attrs{
+		if !attr.path().is_ident("hash_str"){
+			continue;
+		}
+		attr.parse_nested_meta(|meta|{
+			let Some(ident)=meta.path.get_ident()else{
+				return Err(meta.error("unsupported hash_str attribute"));
+			};
+			let value:LitStr=meta.value()?.parse()?;
+			f(ident,value)
+		})?;
+	}
+	Ok(())
+}
+
+fn derive_hash_str_enum(input:DeriveInput)->syn::Result<proc_macro2::TokenStream>{
+	let name=&input.ident;
+	let Data::Enum(data)=&input.data else{
+		return Err(syn::Error::new_spanned(name,"HashStrEnum can only be derived for enums"));
+	};
+	if !input.generics.params.is_empty(){
+		return Err(syn::Error::new_spanned(&input.generics,"HashStrEnum does not support generics"));
+	}
+
+	let mut rename_all=None;
+	parse_attrs(&input.attrs,|ident,value|{
+		if ident=="rename_all"{
+			rename_all=Some(RenameRule::from_str(&value.value()).ok_or_else(||syn::Error::new_spanned(&value,"unknown rename_all rule"))?);
+			Ok(())
+		}else{
+			Err(syn::Error::new_spanned(ident,"unsupported hash_str attribute"))
+		}
+	})?;
+
+	let mut variants=Vec::new();
+	let mut strs=Vec::new();
+	for variant in &data.variants{
+		if !matches!(variant.fields,Fields::Unit){
+			return Err(syn::Error::new_spanned(variant,"HashStrEnum variants must be unit variants"));
+		}
+		let mut rename=None;
+		parse_attrs(&variant.attrs,|ident,value|{
+			if ident=="rename"{
+				rename=Some(value);
+				Ok(())
+			}else{
+				Err(syn::Error::new_spanned(ident,"unsupported hash_str attribute"))
+			}
+		})?;
+		let str=rename.unwrap_or_else(||{
+			let variant_name=variant.ident.to_string();
+			let str=match rename_all{
+				Some(rule)=>rule.apply(&variant_name),
+				None=>variant_name,
+			};
+			LitStr::new(&str,variant.ident.span())
+		});
+		variants.push(&variant.ident);
+		strs.push(str);
+	}
+
+	Ok(quote!{
+		impl #name{
+			/// The HashStr for this variant.
+			#[inline]
+			pub fn as_hash_str(&self)->&'static ::hash_str::HashStr{
+				match self{
+					#(#name::#variants=>::hash_str::hstr!(#strs),)*
+				}
+			}
+		}
+		impl<'a> ::core::convert::TryFrom<&'a ::hash_str::HashStr> for #name{
+			type Error=::hash_str::UnknownVariantError;
+			#[inline]
+			fn try_from(value:&'a ::hash_str::HashStr)->::core::result::Result<Self,Self::Error>{
+				::hash_str::hstr_match!(value{
+					#(#strs=>::core::result::Result::Ok(#name::#variants),)*
+					_=>::core::result::Result::Err(::hash_str::UnknownVariantError),
+				})
+			}
+		}
+	})
+}
+
+/// Derive `as_hash_str` and `TryFrom<&HashStr>` for an enum with unit variants.
+///
+/// Attributes:
+/// - `#[hash_str(rename_all="snake_case")]` on the enum renames all variants.
+///   Supports the same rules as serde.
+/// - `#[hash_str(rename="name")]` on a variant overrides its string.
+#[proc_macro_derive(HashStrEnum,attributes(hash_str))]
+pub fn hash_str_enum(item:TokenStream)->TokenStream{
+	let input=parse_macro_input!(item as DeriveInput);
+	match derive_hash_str_enum(input){
+		Ok(tokens)=>tokens.into(),
+		Err(e)=>e.to_compile_error().into(),
+	}
+}
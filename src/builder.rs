@@ -0,0 +1,242 @@
+use crate::hash::make_hash;
+use crate::hash_str::{HashStr,SIZE_HASH,hash_str_layout};
+use alloc::alloc::{alloc,dealloc,realloc,handle_alloc_error};
+use alloc::boxed::Box;
+use core::mem::ManuallyDrop;
+use core::ptr::NonNull;
+
+/// Builds an owned HashStr in place.  The buffer has the layout of a
+/// HashStr with space for the hash reserved at the front, so finishing
+/// hands the buffer over as a `Box<HashStr>` without copying the string.
+///
+/// The hash is calculated once when the HashStr is created,
+/// because it must match the hash of the whole string.
+///
+/// ```rust
+/// use hash_str::HashStrBuilder;
+/// use std::fmt::Write;
+///
+/// let mut builder=HashStrBuilder::new();
+/// builder.push_str("module");
+/// write!(builder,"::{}",42).unwrap();
+/// let hs=builder.finish();
+/// assert_eq!(hs.as_str(),"module::42");
+/// ```
+pub struct HashStrBuilder{
+	// allocated with hash_str_layout(cap), and the first len bytes
+	// are the hash placeholder followed by a valid str
+	ptr:NonNull<u8>,
+	len:usize,
+	cap:usize,
+}
+
+// SAFETY: the builder uniquely owns its buffer like a Vec<u8>
+unsafe impl Send for HashStrBuilder{}
+unsafe impl Sync for HashStrBuilder{}
+
+impl HashStrBuilder{
+	#[inline]
+	pub fn new()->Self{
+		Self::with_capacity(0)
+	}
+	/// Create a builder with room for a string of `capacity` bytes.
+	#[inline]
+	pub fn with_capacity(capacity:usize)->Self{
+		let layout=hash_str_layout(SIZE_HASH+capacity);
+		// SAFETY: the layout is at least SIZE_HASH bytes
+		let ptr=unsafe{alloc(layout)};
+		let Some(ptr)=NonNull::new(ptr)else{
+			handle_alloc_error(layout);
+		};
+		Self{ptr,len:SIZE_HASH,cap:layout.size()}
+	}
+	/// Reserve room for at least `additional` more bytes.
+	pub fn reserve(&mut self,additional:usize){
+		let needed=self.len.checked_add(additional).expect("capacity overflow");
+		if needed<=self.cap{
+			return;
+		}
+		self.realloc(needed.max(self.cap*2));
+	}
+	// resize the buffer to the HashStr layout for new_len bytes
+	fn realloc(&mut self,new_len:usize){
+		let old_layout=hash_str_layout(self.cap);
+		let new_layout=hash_str_layout(new_len);
+		// SAFETY: ptr was allocated with old_layout, and the new size
+		// is nonzero and keeps the first len bytes
+		let ptr=unsafe{realloc(self.ptr.as_ptr(),old_layout,new_layout.size())};
+		let Some(ptr)=NonNull::new(ptr)else{
+			handle_alloc_error(new_layout);
+		};
+		self.ptr=ptr;
+		self.cap=new_layout.size();
+	}
+	#[inline]
+	pub fn push_str(&mut self,str:&str){
+		self.reserve(str.len());
+		// SAFETY: there is room for str after the first len bytes
+		unsafe{core::ptr::copy_nonoverlapping(str.as_ptr(),self.ptr.as_ptr().add(self.len),str.len())};
+		self.len+=str.len();
+	}
+	#[inline]
+	pub fn push(&mut self,char:char){
+		self.push_str(char.encode_utf8(&mut [0;4]));
+	}
+	#[inline]
+	fn bytes_mut(&mut self)->&mut [u8]{
+		// SAFETY: the first len bytes are initialized
+		unsafe{core::slice::from_raw_parts_mut(self.ptr.as_ptr(),self.len)}
+	}
+	#[inline]
+	pub fn as_str(&self)->&str{
+		// SAFETY: only valid strs are pushed after the hash placeholder
+		unsafe{core::str::from_utf8_unchecked(core::slice::from_raw_parts(
+			self.ptr.as_ptr().add(SIZE_HASH),
+			self.len-SIZE_HASH,
+		))}
+	}
+	#[inline]
+	pub fn len(&self)->usize{
+		self.len-SIZE_HASH
+	}
+	#[inline]
+	pub fn is_empty(&self)->bool{
+		self.len()==0
+	}
+	/// Clear the string, keeping the allocated capacity.
+	#[inline]
+	pub fn clear(&mut self){
+		self.len=SIZE_HASH;
+	}
+	/// Hash the string and turn the buffer into a `Box<HashStr>`.
+	/// Excess capacity is released like `Vec::into_boxed_slice`,
+	/// but the string is never copied into a new allocation.
+	#[inline]
+	pub fn finish(mut self)->Box<HashStr>{
+		let hash=make_hash(self.as_str());
+		self.bytes_mut()[..SIZE_HASH].copy_from_slice(&hash.to_ne_bytes());
+		if hash_str_layout(self.len).size()!=self.cap{
+			self.realloc(self.len);
+		}
+		let mut this=ManuallyDrop::new(self);
+		// SAFETY: the buffer is allocated with the layout of a HashStr of len bytes,
+		// and contains a hash followed by a valid str.  The builder is not dropped.
+		unsafe{HashStr::box_from_alloc(this.bytes_mut())}
+	}
+	/// Intern the string into the provided cache, copying it into the
+	/// host only if it is not already present.  The builder can be
+	/// cleared and reused afterwards.
+	#[cfg(feature="cache")]
+	#[inline]
+	pub fn intern_with<'host>(&self,host:&'host crate::cache::HashStrHost,cache:&mut crate::cache::HashStrCache<'host>)->&'host HashStr{
+		cache.intern_with(host,self.as_str())
+	}
+	/// Intern the string into the global cache.
+	#[cfg(feature="global")]
+	#[inline]
+	pub fn intern(&self)->&'static HashStr{
+		crate::global::get_cache().intern(self.as_str())
+	}
+}
+
+impl Drop for HashStrBuilder{
+	#[inline]
+	fn drop(&mut self){
+		// SAFETY: ptr was allocated with this layout
+		unsafe{dealloc(self.ptr.as_ptr(),hash_str_layout(self.cap))}
+	}
+}
+
+impl Clone for HashStrBuilder{
+	#[inline]
+	fn clone(&self)->Self{
+		let mut builder=Self::with_capacity(self.len());
+		builder.push_str(self.as_str());
+		builder
+	}
+}
+
+impl Default for HashStrBuilder{
+	#[inline]
+	fn default()->Self{
+		Self::new()
+	}
+}
+
+impl core::fmt::Write for HashStrBuilder{
+	#[inline]
+	fn write_str(&mut self,s:&str)->core::fmt::Result{
+		self.push_str(s);
+		Ok(())
+	}
+	#[inline]
+	fn write_char(&mut self,c:char)->core::fmt::Result{
+		self.push(c);
+		Ok(())
+	}
+}
+
+impl core::fmt::Debug for HashStrBuilder{
+	#[inline]
+	fn fmt(&self,f:&mut core::fmt::Formatter<'_>)->core::fmt::Result{
+		f.debug_tuple("HashStrBuilder").field(&self.as_str()).finish()
+	}
+}
+
+/// Format into a reusable thread local buffer and pass the formatted str to `f`.
+/// Avoids allocating a new String for every formatted string.
+#[cfg(feature="cache")]
+pub(crate) fn with_formatted<R>(args:core::fmt::Arguments<'_>,f:impl FnOnce(&str)->R)->R{
+	if let Some(str)=args.as_str(){
+		return f(str);
+	}
+	#[cfg(not(feature="std"))]
+	return f(&alloc::fmt::format(args));
+	#[cfg(feature="std")]
+	thread_local!{
+		static SCRATCH:core::cell::RefCell<alloc::string::String>=const{core::cell::RefCell::new(alloc::string::String::new())};
+	}
+	#[cfg(feature="std")]
+	SCRATCH.with(|scratch|match scratch.try_borrow_mut(){
+		Ok(mut scratch)=>{
+			use core::fmt::Write;
+			scratch.clear();
+			scratch.write_fmt(args).expect("a formatting trait implementation returned an error");
+			f(&scratch)
+		},
+		// formatting while the scratch buffer is in use e.g. from inside `f`
+		Err(_)=>f(&alloc::fmt::format(args)),
+	})
+}
+
+#[test]
+fn test_builder(){
+	use core::fmt::Write;
+	let mut builder=HashStrBuilder::with_capacity(8);
+	builder.push_str("hello");
+	builder.push(' ');
+	let world="world";
+	write!(builder,"{world}").unwrap();
+	assert_eq!(builder.as_str(),"hello world");
+	assert_eq!(builder.len(),11);
+
+	let hs=builder.finish();
+	let anonymous=HashStr::anonymous("hello world".to_owned());
+	assert_eq!(hs.as_str(),"hello world");
+	assert_eq!(hs.precomputed_hash(),anonymous.precomputed_hash());
+	assert_eq!(hs,anonymous);
+
+	// the buffer is handed over without copying the string
+	let mut builder=HashStrBuilder::with_capacity(3);
+	builder.push_str("abc");
+	let ptr=builder.as_str().as_ptr();
+	let clone=builder.clone();
+	let hs=builder.finish();
+	assert_eq!(hs.as_str().as_ptr(),ptr);
+	assert_eq!(hs.as_hash_str_bytes().as_ptr().align_offset(SIZE_HASH),0);
+	assert_eq!(clone.finish(),hs);
+
+	let hs=HashStrBuilder::new().finish();
+	assert_eq!(hs.as_str(),"");
+	assert_eq!(hs.precomputed_hash(),make_hash(""));
+}
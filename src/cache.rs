@@ -1,5 +1,7 @@
 use crate::ornaments::{GetHash,HashedStr};
 use crate::hash_str::{HashStr,SIZE_HASH};
+use crate::builder::with_formatted;
+use alloc::vec::Vec;
 use hashbrown::HashTable;
 
 /// "Host" backing storage for cached HashStrs.
@@ -28,7 +30,8 @@ impl HashStrHost{
 	#[inline]
 	pub(crate) fn alloc_str_with_hash(&self,hash:u64,str:&str)->&HashStr{
 		let hash_str_len=SIZE_HASH+str.len();
-		let layout=bumpalo::core_alloc::alloc::Layout::from_size_align(hash_str_len,SIZE_HASH).unwrap();
+		// the size of a HashStr is padded to its alignment
+		let layout=bumpalo::core_alloc::alloc::Layout::from_size_align(hash_str_len,SIZE_HASH).unwrap().pad_to_align();
 		// alloc empty bytes for new HashStr
 		let new_hash_str_bytes_ptr=self.0.alloc_layout(layout).as_ptr();
 		// SAFETY: bumpalo panics if allocation fails
@@ -44,6 +47,13 @@ impl HashStrHost{
 	}
 }
 
+impl Default for HashStrHost{
+	#[inline]
+	fn default()->Self{
+		Self::new()
+	}
+}
+
 /// Cache of existing entries in a HashStrHost.
 /// Useful to deduplicate a finite set of unique strings,
 /// minimizing the allocation of new strings.
@@ -121,6 +131,38 @@ impl<'host> HashStrCache<'host>{
 		let (hash,str)=(index.get_hash(),index.as_ref());
 		self.intern_str_with_hash(||host.alloc_str_with_hash(hash,str),hash,str)
 	}
+	/// Intern many strings at once, returning the interned HashStrs in input order.
+	/// Hashes are calculated up front and the table is reserved for all items.
+	#[inline]
+	pub fn intern_many<I>(&mut self,host:&'host HashStrHost,iter:I)->Vec<&'host HashStr>
+		where
+			I:IntoIterator,
+			I::Item:GetHash+AsRef<str>,
+	{
+		let items:Vec<_>=iter.into_iter().map(|index|(index.get_hash(),index)).collect();
+		self.reserve(items.len());
+		items.iter().map(|(hash,index)|{
+			let (hash,str)=(*hash,index.as_ref());
+			self.intern_str_with_hash(||host.alloc_str_with_hash(hash,str),hash,str)
+		}).collect()
+	}
+	/// Intern a formatted string.  The string is formatted into scratch space
+	/// and only copied into the provided HashStrHost if it is not already present.
+	///
+	/// ```rust
+	/// use hash_str::{HashStrHost,HashStrCache};
+	///
+	/// let host=HashStrHost::new();
+	/// let mut cache=HashStrCache::new();
+	///
+	/// let (module,name)=("std","fmt");
+	/// let hs=cache.intern_fmt(&host,format_args!("{module}::{name}"));
+	/// assert_eq!(hs.as_str(),"std::fmt");
+	/// ```
+	#[inline]
+	pub fn intern_fmt(&mut self,host:&'host HashStrHost,args:core::fmt::Arguments<'_>)->&'host HashStr{
+		with_formatted(args,|str|self.intern_with(host,str))
+	}
 	#[inline]
 	pub(crate) fn intern_str_with_hash(&mut self,with:impl FnOnce()->&'host HashStr,hash:u64,str:&str)->&'host HashStr{
 		self.entries.entry(
@@ -138,6 +180,10 @@ impl<'host> HashStrCache<'host>{
 		self.entries.len()
 	}
 	#[inline]
+	pub fn is_empty(&self)->bool{
+		self.entries.is_empty()
+	}
+	#[inline]
 	pub fn capacity(&self)->usize{
 		self.entries.capacity()
 	}
@@ -147,6 +193,13 @@ impl<'host> HashStrCache<'host>{
 	}
 }
 
+impl Default for HashStrCache<'_>{
+	#[inline]
+	fn default()->Self{
+		Self::new()
+	}
+}
+
 impl<'host,'a> IntoIterator for &'a HashStrCache<'host>{
 	type Item=&'host HashStr;
 	type IntoIter=core::iter::Copied<hashbrown::hash_table::Iter<'a,&'host HashStr>>;
@@ -239,11 +292,37 @@ fn test_cache(){
 	assert_eq!(a,b);
 }
 
+#[test]
+fn test_intern_many(){
+	let host=HashStrHost::new();
+	let mut cache=HashStrCache::new();
+	let existing=cache.intern_with(&host,"b");
+	let words=cache.intern_many(&host,["a","b","c","a"]);
+	assert_eq!(words,["a","b","c","a"]);
+	assert!(core::ptr::addr_eq(words[1],existing));
+	assert!(core::ptr::addr_eq(words[0],words[3]));
+	assert_eq!(cache.len(),3);
+}
+
+#[test]
+fn test_intern_fmt(){
+	let host=HashStrHost::new();
+	let mut cache=HashStrCache::new();
+	let a=cache.intern_with(&host,"module::name");
+	let (module,name)=("module","name");
+	let b=cache.intern_fmt(&host,format_args!("{module}::{name}"));
+	assert!(core::ptr::addr_eq(a,b));
+	let c=cache.intern_fmt(&host,format_args!("module::name"));
+	assert!(core::ptr::addr_eq(a,c));
+	assert_eq!(cache.len(),1);
+}
+
 // test the readme
+#[cfg(feature="std")]
 #[test]
 fn readme(){
 	use crate::hstr;
-	use crate::hash::HashStrMap;
+	use crate::collections::HashStrMap;
 	use crate::ornaments::UnhashedStr;
 	// string with hash calculated at compile time
 	let hstr_static:&HashStr=hstr!("bruh");
@@ -0,0 +1,176 @@
+//! Generate static HashStr tables from a build script.
+//!
+//! Large tables are slow to build with macros at compile time, so the
+//! hashes are calculated by the build script instead and written into a
+//! source file as a single byte blob of hash prefixed strings.
+//!
+//! ```rust,no_run
+//! // build.rs
+//! let words=["if","else","while"];
+//! let out_dir=std::env::var("OUT_DIR").unwrap();
+//! hash_str::codegen::StaticTable::from_iter(words)
+//!     .write_to_file(std::path::Path::new(&out_dir).join("words.rs"))
+//!     .unwrap();
+//! ```
+//!
+//! ```rust,ignore
+//! // lib.rs
+//! mod words{
+//!     include!(concat!(env!("OUT_DIR"),"/words.rs"));
+//! }
+//! assert_eq!(words::get(0).unwrap().as_str(),"if");
+//! assert_eq!(words::iter().len(),words::LEN);
+//! ```
+use crate::hash::{hash_for_target,HashTarget};
+use crate::hash_str::SIZE_HASH;
+use std::io::{self,Write};
+use std::path::Path;
+
+/// A table of HashStrs to be written into a generated source file.
+/// The generated file contains:
+/// - `pub const LEN:usize` the number of HashStrs
+/// - `pub fn get(index:usize)->Option<&'static HashStr>`
+/// - `pub fn iter()->impl ExactSizeIterator<Item=&'static HashStr>`
+#[derive(Debug)]
+pub struct StaticTable{
+	target:HashTarget,
+	blob:Vec<u8>,
+	// offset and str length of each HashStr in the blob
+	index:Vec<(u32,u32)>,
+}
+
+impl StaticTable{
+	/// Create an empty table.  The hashes are calculated for the target
+	/// the build script is compiling for, read from the `CARGO_CFG_TARGET_ARCH`
+	/// and `CARGO_CFG_TARGET_ENDIAN` environment variables, falling back to
+	/// the current target outside of a build script.
+	pub fn new()->Self{
+		let mut target=HashTarget::NATIVE;
+		if let Ok(arch)=std::env::var("CARGO_CFG_TARGET_ARCH"){
+			target.wide_multiply=HashTarget::WIDE_MULTIPLY_ARCHS.contains(&arch.as_str());
+		}
+		if let Ok(endian)=std::env::var("CARGO_CFG_TARGET_ENDIAN"){
+			target.big_endian=endian=="big";
+		}
+		Self{
+			target,
+			blob:Vec::new(),
+			index:Vec::new(),
+		}
+	}
+	/// Add a string to the table, returning its index.
+	pub fn push(&mut self,str:&str)->usize{
+		let hash=hash_for_target(str,self.target);
+		let hash_bytes=if self.target.big_endian{
+			hash.to_be_bytes()
+		}else{
+			hash.to_le_bytes()
+		};
+		let offset=u32::try_from(self.blob.len()).expect("table too large");
+		let len=u32::try_from(str.len()).expect("str too large");
+		self.blob.extend_from_slice(&hash_bytes);
+		self.blob.extend_from_slice(str.as_bytes());
+		// pad so the next HashStr is aligned
+		self.blob.resize(self.blob.len().next_multiple_of(SIZE_HASH),0);
+		self.index.push((offset,len));
+		self.index.len()-1
+	}
+	#[inline]
+	pub fn len(&self)->usize{
+		self.index.len()
+	}
+	#[inline]
+	pub fn is_empty(&self)->bool{
+		self.index.is_empty()
+	}
+	/// Write the generated source code.
+	pub fn write(&self,w:&mut impl Write)->io::Result<()>{
+		writeln!(w,"// Generated by hash_str::codegen::StaticTable, do not edit.")?;
+		writeln!(w,"#[repr(C,align(8))]")?;
+		writeln!(w,"struct Blob([u8;{}]);",self.blob.len())?;
+		write!(w,"static BLOB:Blob=Blob(*b\"")?;
+		for &byte in &self.blob{
+			match byte{
+				b'"'|b'\\'=>write!(w,"\\{}",byte as char)?,
+				b' '..=b'~'=>write!(w,"{}",byte as char)?,
+				_=>write!(w,"\\x{byte:02x}")?,
+			}
+		}
+		writeln!(w,"\");")?;
+		writeln!(w,"// offset and str length of each HashStr in the blob")?;
+		write!(w,"static INDEX:[(u32,u32);{}]=[",self.index.len())?;
+		for (offset,len) in &self.index{
+			write!(w,"({offset},{len}),")?;
+		}
+		writeln!(w,"];")?;
+		writeln!(w,"/// The number of HashStrs in the table.")?;
+		writeln!(w,"pub const LEN:usize={};",self.index.len())?;
+		writeln!(w,"{}",ACCESSORS)
+	}
+	/// Write the generated source code to a file, typically in `OUT_DIR`.
+	pub fn write_to_file(&self,path:impl AsRef<Path>)->io::Result<()>{
+		let mut file=io::BufWriter::new(std::fs::File::create(path)?);
+		self.write(&mut file)?;
+		file.flush()
+	}
+}
+
+const ACCESSORS:&str="/// Get a HashStr by its index in the table.
+#[inline]
+pub fn get(index:usize)->Option<&'static ::hash_str::HashStr>{
+	let &(offset,len)=INDEX.get(index)?;
+	let start=offset as usize;
+	let bytes=&BLOB.0[start..start+::hash_str::SIZE_HASH+len as usize];
+	// SAFETY: the blob is aligned, and each HashStr is a hash followed by
+	// a valid str, padded so that the next HashStr is also aligned
+	Some(unsafe{::hash_str::HashStr::ref_from_bytes_unchecked(bytes)})
+}
+/// Iterate the HashStrs in table order.
+#[inline]
+pub fn iter()->impl ExactSizeIterator<Item=&'static ::hash_str::HashStr>{
+	(0..LEN).map(|index|get(index).unwrap())
+}";
+
+impl Default for StaticTable{
+	#[inline]
+	fn default()->Self{
+		Self::new()
+	}
+}
+
+impl<S:AsRef<str>> Extend<S> for StaticTable{
+	fn extend<I:IntoIterator<Item=S>>(&mut self,iter:I){
+		for str in iter{
+			self.push(str.as_ref());
+		}
+	}
+}
+
+impl<S:AsRef<str>> FromIterator<S> for StaticTable{
+	fn from_iter<I:IntoIterator<Item=S>>(iter:I)->Self{
+		let mut table=Self::new();
+		table.extend(iter);
+		table
+	}
+}
+
+#[test]
+fn test_static_table(){
+	use crate::hash::make_hash;
+	let words=["","a","quote\"","multi\nline","hash_str"];
+	let table=StaticTable::from_iter(words);
+	assert_eq!(table.len(),words.len());
+	for (&(offset,len),word) in table.index.iter().zip(words){
+		let offset=offset as usize;
+		assert_eq!(offset%SIZE_HASH,0);
+		assert_eq!(len as usize,word.len());
+		assert_eq!(table.blob[offset..offset+SIZE_HASH],make_hash(word).to_ne_bytes());
+		assert_eq!(&table.blob[offset+SIZE_HASH..offset+SIZE_HASH+word.len()],word.as_bytes());
+	}
+	let mut source=Vec::new();
+	table.write(&mut source).unwrap();
+	let source=String::from_utf8(source).unwrap();
+	assert!(source.contains("quote\\\""));
+	assert!(source.contains("multi\\x0aline"));
+	assert!(source.contains("pub const LEN:usize=5;"));
+}
@@ -0,0 +1,351 @@
+use crate::hash::IdentityHasher;
+use crate::hash_str::HashStr;
+use crate::ornaments::UnhashedStr;
+#[cfg(feature="cache")]
+use crate::{ornaments::GetHash,cache::{HashStrHost,HashStrCache}};
+use core::hash::BuildHasherDefault;
+use std::collections::{HashMap,HashSet};
+
+type Inner<K,V>=HashMap<K,V,BuildHasherDefault<IdentityHasher>>;
+type InnerSet<K>=HashSet<K,BuildHasherDefault<IdentityHasher>>;
+
+/// A `HashMap` using `&HashStr` as the key type with a custom `Hasher`
+/// that just uses the precomputed hash for speed instead of calculating it.
+/// Derefs to the standard `HashMap`, so `entry`, `retain` etc. are available.
+/// For iteration in insertion order, use `HashStrIndexMap` with the indexmap feature.
+///
+/// ```rust
+/// use hash_str::{hstr,HashStrMap};
+///
+/// let mut map:HashStrMap<u32>=[(hstr!("a"),1),(hstr!("b"),2)].into_iter().collect();
+/// *map.entry(hstr!("a")).or_default()+=1;
+/// assert_eq!(map.get_str("a"),Some(&2));
+/// map.retain(|_,&mut v|v==2);
+/// assert_eq!(map.len(),2);
+/// ```
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct HashStrMap<'a,V>(Inner<&'a HashStr,V>);
+
+impl<'a,V> HashStrMap<'a,V>{
+	#[inline]
+	pub fn new()->Self{
+		Self(HashMap::default())
+	}
+	#[inline]
+	pub fn with_capacity(capacity:usize)->Self{
+		Self(HashMap::with_capacity_and_hasher(capacity,Default::default()))
+	}
+	/// Fetch a value by str, hashing it on the fly.
+	#[inline]
+	pub fn get_str(&self,key:&str)->Option<&V>{
+		self.0.get(UnhashedStr::from_ref(key))
+	}
+	#[inline]
+	pub fn get_str_mut(&mut self,key:&str)->Option<&mut V>{
+		self.0.get_mut(UnhashedStr::from_ref(key))
+	}
+	#[inline]
+	pub fn get_key_value_str(&self,key:&str)->Option<(&'a HashStr,&V)>{
+		self.0.get_key_value(UnhashedStr::from_ref(key)).map(|(&key,value)|(key,value))
+	}
+	#[inline]
+	pub fn contains_str(&self,key:&str)->bool{
+		self.0.contains_key(UnhashedStr::from_ref(key))
+	}
+	#[inline]
+	pub fn remove_str(&mut self,key:&str)->Option<V>{
+		self.0.remove(UnhashedStr::from_ref(key))
+	}
+	#[inline]
+	pub fn from_inner(inner:Inner<&'a HashStr,V>)->Self{
+		Self(inner)
+	}
+	#[inline]
+	pub fn into_inner(self)->Inner<&'a HashStr,V>{
+		self.0
+	}
+}
+
+#[cfg(feature="cache")]
+impl<'host,V> HashStrMap<'host,V>{
+	/// Insert entries keyed by any `GetHash` key source, such as `&HashStr`,
+	/// `HashedStr` or `&str`, interning the keys into the provided cache.
+	#[inline]
+	pub fn extend_interned<K:GetHash+AsRef<str>>(&mut self,cache:&mut HashStrCache<'host>,host:&'host HashStrHost,iter:impl IntoIterator<Item=(K,V)>){
+		self.0.extend(iter.into_iter().map(|(key,value)|(cache.intern_with(host,key),value)))
+	}
+}
+#[cfg(feature="global")]
+impl<V> HashStrMap<'static,V>{
+	/// Insert entries keyed by any `GetHash` key source, interning the keys
+	/// into the global cache.  Interned strings are never freed.
+	#[inline]
+	pub fn extend_global<K:GetHash+AsRef<str>>(&mut self,iter:impl IntoIterator<Item=(K,V)>){
+		let cache=crate::global::get_cache();
+		self.0.extend(iter.into_iter().map(|(key,value)|(cache.intern(key),value)))
+	}
+}
+
+impl<V> Default for HashStrMap<'_,V>{
+	#[inline]
+	fn default()->Self{
+		Self::new()
+	}
+}
+
+impl<'a,V> core::ops::Deref for HashStrMap<'a,V>{
+	type Target=Inner<&'a HashStr,V>;
+	#[inline]
+	fn deref(&self)->&Self::Target{
+		&self.0
+	}
+}
+impl<V> core::ops::DerefMut for HashStrMap<'_,V>{
+	#[inline]
+	fn deref_mut(&mut self)->&mut Self::Target{
+		&mut self.0
+	}
+}
+
+/// Any key source which lends a HashStr, such as `&HashStr` or `&Box<HashStr>`.
+/// Intern other key sources explicitly with `extend_interned` or `extend_global`.
+impl<'a,K:Into<&'a HashStr>,V> Extend<(K,V)> for HashStrMap<'a,V>{
+	#[inline]
+	fn extend<I:IntoIterator<Item=(K,V)>>(&mut self,iter:I){
+		self.0.extend(iter.into_iter().map(|(key,value)|(key.into(),value)))
+	}
+}
+impl<'a,K:Into<&'a HashStr>,V> FromIterator<(K,V)> for HashStrMap<'a,V>{
+	#[inline]
+	fn from_iter<I:IntoIterator<Item=(K,V)>>(iter:I)->Self{
+		let mut map=Self::new();
+		map.extend(iter);
+		map
+	}
+}
+
+impl<'a,V> IntoIterator for HashStrMap<'a,V>{
+	type Item=(&'a HashStr,V);
+	type IntoIter=std::collections::hash_map::IntoIter<&'a HashStr,V>;
+	#[inline]
+	fn into_iter(self)->Self::IntoIter{
+		self.0.into_iter()
+	}
+}
+impl<'m,'a,V> IntoIterator for &'m HashStrMap<'a,V>{
+	type Item=(&'m &'a HashStr,&'m V);
+	type IntoIter=std::collections::hash_map::Iter<'m,&'a HashStr,V>;
+	#[inline]
+	fn into_iter(self)->Self::IntoIter{
+		self.0.iter()
+	}
+}
+impl<'m,'a,V> IntoIterator for &'m mut HashStrMap<'a,V>{
+	type Item=(&'m &'a HashStr,&'m mut V);
+	type IntoIter=std::collections::hash_map::IterMut<'m,&'a HashStr,V>;
+	#[inline]
+	fn into_iter(self)->Self::IntoIter{
+		self.0.iter_mut()
+	}
+}
+
+/// A `HashSet` using `&HashStr` as the key type with a custom `Hasher`
+/// that just uses the precomputed hash for speed instead of calculating it.
+/// Derefs to the standard `HashSet`, and supports set algebra with operators.
+/// For iteration in insertion order, use `HashStrIndexSet` with the indexmap feature.
+///
+/// ```rust
+/// use hash_str::{hstr,HashStrSet};
+///
+/// let a:HashStrSet=[hstr!("a"),hstr!("b")].into_iter().collect();
+/// let b:HashStrSet=[hstr!("b"),hstr!("c")].into_iter().collect();
+/// assert_eq!(&a & &b,[hstr!("b")].into_iter().collect());
+/// assert_eq!((&a|&b).len(),3);
+/// assert!((&a-&b).contains_str("a"));
+/// ```
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct HashStrSet<'a>(InnerSet<&'a HashStr>);
+
+impl<'a> HashStrSet<'a>{
+	#[inline]
+	pub fn new()->Self{
+		Self(HashSet::default())
+	}
+	#[inline]
+	pub fn with_capacity(capacity:usize)->Self{
+		Self(HashSet::with_capacity_and_hasher(capacity,Default::default()))
+	}
+	/// Fetch the HashStr equal to a str, hashing it on the fly.
+	#[inline]
+	pub fn get_str(&self,key:&str)->Option<&'a HashStr>{
+		self.0.get(UnhashedStr::from_ref(key)).copied()
+	}
+	#[inline]
+	pub fn contains_str(&self,key:&str)->bool{
+		self.0.contains(UnhashedStr::from_ref(key))
+	}
+	#[inline]
+	pub fn remove_str(&mut self,key:&str)->bool{
+		self.0.remove(UnhashedStr::from_ref(key))
+	}
+	#[inline]
+	pub fn from_inner(inner:InnerSet<&'a HashStr>)->Self{
+		Self(inner)
+	}
+	#[inline]
+	pub fn into_inner(self)->InnerSet<&'a HashStr>{
+		self.0
+	}
+}
+
+#[cfg(feature="cache")]
+impl<'host> HashStrSet<'host>{
+	/// Insert any `GetHash` key source, such as `&HashStr`, `HashedStr`
+	/// or `&str`, interning the keys into the provided cache.
+	#[inline]
+	pub fn extend_interned<K:GetHash+AsRef<str>>(&mut self,cache:&mut HashStrCache<'host>,host:&'host HashStrHost,iter:impl IntoIterator<Item=K>){
+		self.0.extend(iter.into_iter().map(|key|cache.intern_with(host,key)))
+	}
+}
+#[cfg(feature="global")]
+impl HashStrSet<'static>{
+	/// Insert any `GetHash` key source, interning the keys into the
+	/// global cache.  Interned strings are never freed.
+	#[inline]
+	pub fn extend_global<K:GetHash+AsRef<str>>(&mut self,iter:impl IntoIterator<Item=K>){
+		let cache=crate::global::get_cache();
+		self.0.extend(iter.into_iter().map(|key|cache.intern(key)))
+	}
+}
+
+impl Default for HashStrSet<'_>{
+	#[inline]
+	fn default()->Self{
+		Self::new()
+	}
+}
+
+impl<'a> core::ops::Deref for HashStrSet<'a>{
+	type Target=InnerSet<&'a HashStr>;
+	#[inline]
+	fn deref(&self)->&Self::Target{
+		&self.0
+	}
+}
+impl core::ops::DerefMut for HashStrSet<'_>{
+	#[inline]
+	fn deref_mut(&mut self)->&mut Self::Target{
+		&mut self.0
+	}
+}
+
+/// Any key source which lends a HashStr, such as `&HashStr` or `&Box<HashStr>`.
+/// Intern other key sources explicitly with `extend_interned` or `extend_global`.
+impl<'a,K:Into<&'a HashStr>> Extend<K> for HashStrSet<'a>{
+	#[inline]
+	fn extend<I:IntoIterator<Item=K>>(&mut self,iter:I){
+		self.0.extend(iter.into_iter().map(Into::into))
+	}
+}
+impl<'a,K:Into<&'a HashStr>> FromIterator<K> for HashStrSet<'a>{
+	#[inline]
+	fn from_iter<I:IntoIterator<Item=K>>(iter:I)->Self{
+		let mut set=Self::new();
+		set.extend(iter);
+		set
+	}
+}
+
+impl<'a> IntoIterator for HashStrSet<'a>{
+	type Item=&'a HashStr;
+	type IntoIter=std::collections::hash_set::IntoIter<&'a HashStr>;
+	#[inline]
+	fn into_iter(self)->Self::IntoIter{
+		self.0.into_iter()
+	}
+}
+impl<'s,'a> IntoIterator for &'s HashStrSet<'a>{
+	type Item=&'s &'a HashStr;
+	type IntoIter=std::collections::hash_set::Iter<'s,&'a HashStr>;
+	#[inline]
+	fn into_iter(self)->Self::IntoIter{
+		self.0.iter()
+	}
+}
+
+macro_rules! impl_set_op{
+	($op:ident,$method:ident,$set_method:ident)=>{
+		impl<'a> core::ops::$op<&HashStrSet<'a>> for &HashStrSet<'a>{
+			type Output=HashStrSet<'a>;
+			#[inline]
+			fn $method(self,rhs:&HashStrSet<'a>)->HashStrSet<'a>{
+				self.0.$set_method(&rhs.0).copied().collect()
+			}
+		}
+	};
+}
+impl_set_op!(BitOr,bitor,union);
+impl_set_op!(BitAnd,bitand,intersection);
+impl_set_op!(BitXor,bitxor,symmetric_difference);
+impl_set_op!(Sub,sub,difference);
+
+#[test]
+fn test_collections(){
+	use alloc::boxed::Box;
+	let owned:[Box<HashStr>;3]=["a","b","c"].map(|s|HashStr::anonymous(s.to_owned()));
+	let mut map:HashStrMap<u32>=owned.iter().zip(0..).collect();
+	assert_eq!(map.get_str("b"),Some(&1));
+	*map.get_str_mut("b").unwrap()+=10;
+	assert_eq!(map.get(&*owned[1]),Some(&11));
+	assert_eq!(map.get_key_value_str("c").map(|(k,_)|k.as_str()),Some("c"));
+	assert_eq!(map.remove_str("a"),Some(0));
+	assert!(!map.contains_str("a"));
+	map.extend([(&*owned[0],5)]);
+	assert_eq!((&map).into_iter().map(|(_,v)|v).sum::<u32>(),18);
+
+	let a:HashStrSet=owned[..2].iter().collect();
+	let b:HashStrSet=owned[1..].iter().collect();
+	assert_eq!(a.get_str("a").map(HashStr::as_str),Some("a"));
+	assert_eq!((&a^&b).len(),2);
+	assert!((&a^&b).contains_str("c"));
+	assert!((&a & &b).is_subset(&a));
+	let mut c=&a|&b;
+	assert!(c.remove_str("c"));
+	assert_eq!(c,a);
+
+	#[cfg(feature="cache")]
+	{
+		use crate::ornaments::HashedStr;
+		let host=HashStrHost::new();
+		let mut cache=HashStrCache::new();
+		let mut map:HashStrMap<u32>=HashStrMap::new();
+		map.extend_interned(&mut cache,&host,owned.iter().map(|key|&**key).zip(0..));
+		map.extend_interned(&mut cache,&host,[(HashedStr::new("d"),3)]);
+		map.extend_interned(&mut cache,&host,[("a",4)]);
+		assert_eq!(map.len(),4);
+		assert_eq!(map.get_str("a"),Some(&4));
+		assert!(core::ptr::addr_eq(map.get_key_value_str("d").unwrap().0,cache.get("d").unwrap()));
+
+		let mut set=HashStrSet::new();
+		set.extend_interned(&mut cache,&host,[&*HashStr::anonymous("e".to_owned())]);
+		assert!(core::ptr::addr_eq(set.get_str("e").unwrap(),cache.get("e").unwrap()));
+		assert_eq!(cache.len(),5);
+	}
+
+	#[cfg(feature="global")]
+	{
+		use crate::ornaments::HashedStr;
+		let global=crate::global::get_cache();
+		let mut map:HashStrMap<'static,u32>=HashStrMap::new();
+		map.extend_global(owned.iter().map(|key|&**key).zip(0..));
+		map.extend_global([(crate::hstr!("d"),3)]);
+		map.extend_global([(HashedStr::new("e"),4)]);
+		assert_eq!(map.len(),5);
+		assert!(core::ptr::addr_eq(map.get_key_value_str("a").unwrap().0,global.intern("a")));
+		assert!(core::ptr::addr_eq(map.get_key_value_str("e").unwrap().0,global.intern("e")));
+
+		let mut set:HashStrSet<'static>=HashStrSet::new();
+		set.extend_global(["a","b"]);
+		assert!(core::ptr::addr_eq(set.get_str("b").unwrap(),global.intern("b")));
+	}
+}
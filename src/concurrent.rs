@@ -0,0 +1,309 @@
+use core::ops::Deref;
+use hashbrown::HashTable;
+use parking_lot::{RwLock,RwLockWriteGuard,MappedRwLockReadGuard,MappedRwLockWriteGuard,RwLockReadGuard};
+use crate::hash::{whichshard,NUM_BINS};
+use crate::hash_str::HashStr;
+use crate::map::get_key_hash;
+use crate::ornaments::{GetHash,HashedStr};
+
+/// A concurrent map from HashStr keys to values.  The shard is chosen from the
+/// middle bits of the precomputed hash, and each shard is a table indexed by
+/// the same precomputed hash, so keys are never rehashed.
+/// The key type may be `&HashStr`, `Box<HashStr>` or `Arc<HashStr>`.
+///
+/// Guards returned by `get`, `get_mut` and `entry` hold a lock on a shard.
+/// Accessing the same map from the same thread while holding one may deadlock.
+///
+/// ```rust
+/// use hash_str::{ConcurrentHashStrMap,HashStr};
+///
+/// let map=ConcurrentHashStrMap::<Box<HashStr>,u32>::new();
+/// std::thread::scope(|s|{
+///     for _ in 0..4{
+///         s.spawn(||*map.entry("bruh").or_insert(0)+=1);
+///     }
+/// });
+/// assert_eq!(*map.get("bruh").unwrap(),4);
+/// ```
+pub struct ConcurrentHashStrMap<K,V>([RwLock<HashTable<(K,V)>>; NUM_BINS]);
+
+impl<K,V> ConcurrentHashStrMap<K,V>{
+	#[inline]
+	pub fn new()->Self{
+		Self(core::array::from_fn(|_|RwLock::new(HashTable::new())))
+	}
+	/// The number of entries.  Each shard is locked in turn,
+	/// so entries inserted concurrently may be missed.
+	#[inline]
+	pub fn len(&self)->usize{
+		self.0.iter().map(|shard|shard.read().len()).sum()
+	}
+	#[inline]
+	pub fn is_empty(&self)->bool{
+		self.0.iter().all(|shard|shard.read().is_empty())
+	}
+	#[inline]
+	pub fn clear(&self){
+		for shard in &self.0{
+			shard.write().clear();
+		}
+	}
+	/// Visit each entry.  Each shard is read locked while it is visited.
+	#[inline]
+	pub fn for_each(&self,mut f:impl FnMut(&K,&V)){
+		for shard in &self.0{
+			for (key,value) in shard.read().iter(){
+				f(key,value);
+			}
+		}
+	}
+	/// Visit each entry mutably.  Each shard is write locked while it is visited.
+	#[inline]
+	pub fn for_each_mut(&self,mut f:impl FnMut(&K,&mut V)){
+		for shard in &self.0{
+			for (key,value) in shard.write().iter_mut(){
+				f(key,value);
+			}
+		}
+	}
+	/// Keep the entries for which `f` returns true.
+	#[inline]
+	pub fn retain(&self,mut f:impl FnMut(&K,&mut V)->bool){
+		for shard in &self.0{
+			shard.write().retain(|(key,value)|f(key,value));
+		}
+	}
+	/// Iterate the entries mutably.  The map is borrowed exclusively, so no shard is locked.
+	#[inline]
+	pub fn iter_mut(&mut self)->impl Iterator<Item=(&K,&mut V)>{
+		self.0.iter_mut().flat_map(|shard|shard.get_mut().iter_mut().map(|(key,value)|(&*key,value)))
+	}
+}
+
+impl<K:Deref<Target=HashStr>,V> ConcurrentHashStrMap<K,V>{
+	#[inline]
+	fn shard(&self,hash:u64)->&RwLock<HashTable<(K,V)>>{
+		&self.0[whichshard(hash)]
+	}
+	/// Fetch a value, utilizing the precalculated hash if possible.
+	#[inline]
+	pub fn get<'a>(&self,index:impl GetHash+Into<&'a str>)->Option<MappedRwLockReadGuard<'_,V>>{
+		self.get_hashed(HashedStr{hash:index.get_hash(),str:index.into()})
+	}
+	/// Fetch a value using the precomputed hash.
+	#[inline]
+	pub fn get_hashed(&self,index:HashedStr<'_>)->Option<MappedRwLockReadGuard<'_,V>>{
+		RwLockReadGuard::try_map(self.shard(index.hash).read(),|shard|{
+			shard.find(index.hash,|(key,_)|key.as_str()==index.str).map(|(_,value)|value)
+		}).ok()
+	}
+	#[inline]
+	pub fn get_mut<'a>(&self,index:impl GetHash+Into<&'a str>)->Option<MappedRwLockWriteGuard<'_,V>>{
+		let index=HashedStr{hash:index.get_hash(),str:index.into()};
+		RwLockWriteGuard::try_map(self.shard(index.hash).write(),|shard|{
+			shard.find_mut(index.hash,|(key,_)|key.as_str()==index.str).map(|(_,value)|value)
+		}).ok()
+	}
+	#[inline]
+	pub fn contains_key<'a>(&self,index:impl GetHash+Into<&'a str>)->bool{
+		self.get(index).is_some()
+	}
+	/// Insert a value, returning the previous value.  The existing key is kept.
+	#[inline]
+	pub fn insert(&self,key:K,value:V)->Option<V>{
+		let (hash,str)=(key.precomputed_hash(),key.as_str());
+		let mut shard=self.shard(hash).write();
+		match shard.find_mut(hash,|(k,_)|k.as_str()==str){
+			Some((_,v))=>Some(core::mem::replace(v,value)),
+			None=>{
+				shard.insert_unique(hash,(key,value),get_key_hash);
+				None
+			},
+		}
+	}
+	#[inline]
+	pub fn remove<'a>(&self,index:impl GetHash+Into<&'a str>)->Option<(K,V)>{
+		let (hash,str)=(index.get_hash(),index.into());
+		match self.shard(hash).write().find_entry(hash,|(key,_)|key.as_str()==str){
+			Ok(entry)=>Some(entry.remove().0),
+			Err(_)=>None,
+		}
+	}
+	/// Get the entry for a string, hashing it at most once.
+	/// The shard stays write locked until the entry is dropped.
+	#[inline]
+	pub fn entry<'a>(&self,index:impl GetHash+Into<&'a str>)->ConcurrentEntry<'_,'a,K,V>{
+		self.entry_hashed(HashedStr{hash:index.get_hash(),str:index.into()})
+	}
+	/// Get the entry using the precomputed hash.
+	#[inline]
+	pub fn entry_hashed<'a>(&self,index:HashedStr<'a>)->ConcurrentEntry<'_,'a,K,V>{
+		ConcurrentEntry{
+			shard:self.shard(index.hash).write(),
+			key:index,
+		}
+	}
+}
+
+impl<K,V> Default for ConcurrentHashStrMap<K,V>{
+	#[inline]
+	fn default()->Self{
+		Self::new()
+	}
+}
+
+impl<K,V> IntoIterator for ConcurrentHashStrMap<K,V>{
+	type Item=(K,V);
+	type IntoIter=core::iter::FlatMap<core::array::IntoIter<RwLock<HashTable<(K,V)>>,NUM_BINS>,hashbrown::hash_table::IntoIter<(K,V)>,fn(RwLock<HashTable<(K,V)>>)->hashbrown::hash_table::IntoIter<(K,V)>>;
+	#[inline]
+	fn into_iter(self)->Self::IntoIter{
+		self.0.into_iter().flat_map(|shard|shard.into_inner().into_iter())
+	}
+}
+
+impl<K:Deref<Target=HashStr>,V> FromIterator<(K,V)> for ConcurrentHashStrMap<K,V>{
+	fn from_iter<I:IntoIterator<Item=(K,V)>>(iter:I)->Self{
+		let map=Self::new();
+		for (key,value) in iter{
+			map.insert(key,value);
+		}
+		map
+	}
+}
+
+/// An entry of a [`ConcurrentHashStrMap`], holding the write lock of its shard.
+pub struct ConcurrentEntry<'m,'a,K,V>{
+	shard:RwLockWriteGuard<'m,HashTable<(K,V)>>,
+	key:HashedStr<'a>,
+}
+
+impl<'m,'a,K:Deref<Target=HashStr>,V> ConcurrentEntry<'m,'a,K,V>{
+	#[inline]
+	pub fn key(&self)->HashedStr<'a>{
+		self.key
+	}
+	#[inline]
+	pub fn get(&self)->Option<&V>{
+		let key=self.key;
+		self.shard.find(key.hash,|(k,_)|k.as_str()==key.str).map(|(_,value)|value)
+	}
+	#[inline]
+	pub fn get_mut(&mut self)->Option<&mut V>{
+		let key=self.key;
+		self.shard.find_mut(key.hash,|(k,_)|k.as_str()==key.str).map(|(_,value)|value)
+	}
+	#[inline]
+	pub fn is_occupied(&self)->bool{
+		self.get().is_some()
+	}
+	#[inline]
+	pub fn and_modify(mut self,f:impl FnOnce(&mut V))->Self{
+		if let Some(value)=self.get_mut(){
+			f(value);
+		}
+		self
+	}
+	#[inline]
+	pub fn remove(mut self)->Option<(K,V)>{
+		let key=self.key;
+		match self.shard.find_entry(key.hash,|(k,_)|k.as_str()==key.str){
+			Ok(entry)=>Some(entry.remove().0),
+			Err(_)=>None,
+		}
+	}
+	/// Insert the value with a key created by `make_key` if the entry is vacant.
+	#[inline]
+	pub fn or_insert_keyed(self,make_key:impl FnOnce(HashedStr<'a>)->K,value:V)->MappedRwLockWriteGuard<'m,V>{
+		self.insert_with(make_key,|_|value)
+	}
+	#[inline]
+	fn insert_with(self,make_key:impl FnOnce(HashedStr<'a>)->K,value:impl FnOnce(HashedStr<'a>)->V)->MappedRwLockWriteGuard<'m,V>{
+		let key=self.key;
+		RwLockWriteGuard::map(self.shard,|shard|{
+			&mut shard.entry(
+				key.hash,
+				|(k,_)|k.as_str()==key.str,
+				get_key_hash,
+			).or_insert_with(||(make_key(key),value(key))).into_mut().1
+		})
+	}
+}
+
+impl<'m,'a,K:From<HashedStr<'a>>+Deref<Target=HashStr>,V> ConcurrentEntry<'m,'a,K,V>{
+	#[inline]
+	pub fn or_insert(self,value:V)->MappedRwLockWriteGuard<'m,V>{
+		self.or_insert_with(||value)
+	}
+	#[inline]
+	pub fn or_insert_with(self,f:impl FnOnce()->V)->MappedRwLockWriteGuard<'m,V>{
+		self.insert_with(K::from,|_|f())
+	}
+	/// Insert a value computed from the key if the entry is vacant.
+	#[inline]
+	pub fn or_insert_with_key(self,f:impl FnOnce(HashedStr<'a>)->V)->MappedRwLockWriteGuard<'m,V>{
+		self.insert_with(K::from,f)
+	}
+	#[inline]
+	pub fn or_default(self)->MappedRwLockWriteGuard<'m,V> where V:Default{
+		self.or_insert_with(V::default)
+	}
+}
+
+#[test]
+fn test_concurrent_map(){
+	let words=["a","b","c","a","b","a"];
+	let map=ConcurrentHashStrMap::<Box<HashStr>,u32>::new();
+	std::thread::scope(|s|{
+		for _ in 0..4{
+			s.spawn(||{
+				for word in words{
+					*map.entry(word).or_default()+=1;
+				}
+			});
+		}
+	});
+	assert_eq!(map.len(),3);
+	assert_eq!(*map.get("a").unwrap(),12);
+	assert_eq!(*map.get_hashed(HashedStr::new("b")).unwrap(),8);
+	*map.get_mut("c").unwrap()=0;
+	assert_eq!(map.insert(HashStr::anonymous("c".into()),1),Some(0));
+
+	let mut sum=0;
+	map.for_each(|_,&value|sum+=value);
+	assert_eq!(sum,21);
+
+	assert!(map.entry("b").and_modify(|v|*v=0).is_occupied());
+	assert_eq!(map.entry("b").remove().map(|(k,v)|(k.as_str().to_owned(),v)),Some(("b".to_owned(),0)));
+	assert_eq!(map.remove("a").map(|(_,v)|v),Some(12));
+	assert!(!map.contains_key("a"));
+	assert_eq!(*map.entry("d").or_insert_with_key(|key|key.str.len() as u32),1);
+	assert_eq!(*map.entry("d").or_insert_keyed(|_|unreachable!(),5),1);
+	let host=crate::cache::HashStrHost::new();
+	let borrowed=ConcurrentHashStrMap::<&HashStr,u32>::new();
+	*borrowed.entry("e").or_insert_keyed(|key|host.alloc_str_with_hash(key.hash,key.str),0)+=1;
+	assert_eq!(*borrowed.get("e").unwrap(),1);
+
+	let mut map=map;
+	for (_,value) in map.iter_mut(){
+		*value+=1;
+	}
+	let mut entries:Vec<(String,u32)>=map.into_iter().map(|(key,value)|(key.as_str().to_owned(),value)).collect();
+	entries.sort();
+	assert_eq!(entries,[("c".to_owned(),2),("d".to_owned(),2)]);
+
+	let map:ConcurrentHashStrMap<Box<HashStr>,u32>=entries.iter().map(|(key,value)|(HashStr::anonymous(key.clone()),*value)).collect();
+	map.retain(|_,_|false);
+	assert!(map.is_empty());
+}
+
+#[test]
+fn test_concurrent_map_shards(){
+	// keys spread over every shard, and within a shard the bucket and tag bits still vary
+	let map:ConcurrentHashStrMap<Box<HashStr>,usize>=(0..4096).map(|i|(HashStr::anonymous(format!("shard{i}")),i)).collect();
+	assert_eq!(map.len(),4096);
+	assert!(map.0.iter().all(|shard|!shard.read().is_empty()));
+	let shard=map.0[0].read();
+	let low:std::collections::HashSet<u64>=shard.iter().map(|(key,_)|key.precomputed_hash()&0x3f).collect();
+	let top:std::collections::HashSet<u64>=shard.iter().map(|(key,_)|key.precomputed_hash()>>57).collect();
+	assert!(low.len()>16&&top.len()>16);
+}
@@ -1,25 +1,35 @@
-use parking_lot::Mutex;
+use parking_lot::{Mutex,MutexGuard};
 use crate::ornaments::{GetHash,HashedStr};
 use crate::hash_str::HashStr;
+use crate::builder::with_formatted;
 use crate::cache::{HashStrHost,HashStrCache,Presence};
 
-// Number of bins (shards) for map
-const BIN_SHIFT: usize = 6;
-const NUM_BINS: usize = 1 << BIN_SHIFT;
-// Shift for top bits to determine bin a hash falls into
-const TOP_SHIFT: usize =
-    8 * core::mem::size_of::<usize>() - BIN_SHIFT;
+pub(crate) use crate::hash::{whichbin,NUM_BINS};
 
 /// The type used for the global string cache.
 ///
-/// This is exposed to allow e.g. serialization of the data returned by the
-/// [`cache()`] function.
+/// This is exposed to allow e.g. iteration and serialization of the data
+/// returned by the [`get_cache()`] function.
 #[repr(transparent)]
 pub struct Bins<'host>([Mutex<HostCache<'host>>; NUM_BINS]);
 
-struct HostCache<'host>{
+pub(crate) struct HostCache<'host>{
 	host:HashStrHost,
-	cache:HashStrCache<'host>,
+	pub(crate) cache:HashStrCache<'host>,
+}
+impl<'host> HostCache<'host>{
+	#[inline]
+	pub(crate) fn intern_str_with_hash(&mut self,hash:u64,str:&str)->&'host HashStr{
+		let HostCache{cache,host}=self;
+		cache.intern_str_with_hash(||{
+			// SAFETY: this pointer is created to be valid for the
+			// duration of the .alloc borrow of host, but we know
+			// that it is actually valid for the lifetime of
+			// HostCache.host, which in this case is 'static
+			let ptr=host.alloc_str_with_hash(hash,str) as *const HashStr;
+			unsafe{&*ptr}
+		},hash,str)
+	}
 }
 
 lazy_static::lazy_static!{
@@ -49,12 +59,11 @@ pub unsafe fn _clear_cache(){
 	}
 }
 
-// Use the top bits of the hash to choose a bin
-#[inline]
-fn whichbin(hash: u64) -> usize {
-    ((hash >> TOP_SHIFT as u64) % NUM_BINS as u64) as usize
-}
 impl<'host> Bins<'host>{
+	#[inline]
+	pub(crate) fn lock_bin(&self,bin:usize)->MutexGuard<'_,HostCache<'host>>{
+		self.0[bin].lock()
+	}
 	/// Get a string from the global cache.
 	#[inline]
 	pub fn get(&self,index:impl GetHash+AsRef<str>)->Option<&'host HashStr>{
@@ -84,17 +93,57 @@ impl<'host> Bins<'host>{
 	pub fn intern(&self,index:impl GetHash+AsRef<str>)->&'host HashStr{
 		self.intern_str_with_hash(index.get_hash(),index.as_ref())
 	}
+	/// Intern a formatted string into the global cache.  The string is formatted
+	/// into scratch space and only copied into the cache if it is not already present.
+	#[inline]
+	pub fn intern_fmt(&self,args:core::fmt::Arguments<'_>)->&'host HashStr{
+		with_formatted(args,|str|self.intern(str))
+	}
 	#[inline]
 	pub(crate) fn intern_str_with_hash(&self,hash:u64,str:&str)->&'host HashStr{
-		let HostCache{cache,host}=&mut*self.0[whichbin(hash)].lock();
-		cache.intern_str_with_hash(||{
-			// SAFETY: this pointer is created to be valid for the
-			// duration of the .alloc borrow of host, but we know
-			// that it is actually valid for the lifetime of
-			// HostCache.host, which in this case is 'static
-			let ptr=host.alloc_str_with_hash(hash,str) as *const HashStr;
-			unsafe{&*ptr}
-		},hash,str)
+		self.0[whichbin(hash)].lock().intern_str_with_hash(hash,str)
+	}
+	/// Iterate the HashStrs in the global cache.  Each bin is locked only while
+	/// its HashStrs are collected, so strings interned concurrently may be missed.
+	#[inline]
+	pub fn iter(&self)->impl Iterator<Item=&'host HashStr>+'_{
+		self.0.iter().flat_map(|bin|bin.lock().cache.iter().collect::<Vec<_>>())
+	}
+	/// The number of HashStrs in the global cache.
+	#[inline]
+	pub fn len(&self)->usize{
+		self.0.iter().map(|bin|bin.lock().cache.len()).sum()
+	}
+	#[inline]
+	pub fn is_empty(&self)->bool{
+		self.0.iter().all(|bin|bin.lock().cache.is_empty())
+	}
+	/// Intern many strings into the global cache, returning the interned HashStrs
+	/// in input order.  Hashes are calculated up front and the items are grouped
+	/// by bin so that each bin is locked only once.
+	#[inline]
+	pub fn intern_many<I>(&self,iter:I)->Vec<&'host HashStr>
+		where
+			I:IntoIterator,
+			I::Item:GetHash+AsRef<str>,
+	{
+		let items:Vec<_>=iter.into_iter().map(|index|{
+			let hash=index.get_hash();
+			(whichbin(hash),hash,index)
+		}).collect();
+		// item indices sorted by bin
+		let mut order:Vec<usize>=(0..items.len()).collect();
+		order.sort_unstable_by_key(|&i|items[i].0);
+		let mut interned=vec![None;items.len()];
+		for group in order.chunk_by(|&a,&b|items[a].0==items[b].0){
+			let bin=&mut*self.lock_bin(items[group[0]].0);
+			bin.cache.reserve(group.len());
+			for &i in group{
+				let (_,hash,index)=&items[i];
+				interned[i]=Some(bin.intern_str_with_hash(*hash,index.as_ref()));
+			}
+		}
+		interned.into_iter().map(|hash_str|hash_str.unwrap()).collect()
 	}
 }
 
@@ -124,3 +173,22 @@ impl_from_borrowed!(&String);
 use std::borrow::Cow;
 impl_from_owned!(Cow<'_,str>);
 impl_from_borrowed!(&Cow<'_,str>);
+
+#[test]
+fn test_intern_many(){
+	let cache=get_cache();
+	let existing=cache.intern("global_intern_many_b");
+	let words=cache.intern_many(["global_intern_many_a","global_intern_many_b","global_intern_many_c","global_intern_many_a"]);
+	assert_eq!(words,["global_intern_many_a","global_intern_many_b","global_intern_many_c","global_intern_many_a"]);
+	assert!(core::ptr::addr_eq(words[1],existing));
+	assert!(core::ptr::addr_eq(words[0],words[3]));
+}
+
+#[test]
+fn test_iter(){
+	let cache=get_cache();
+	let hs=cache.intern("global_iter");
+	assert!(cache.iter().any(|s|core::ptr::addr_eq(s,hs)));
+	assert!(!cache.is_empty());
+	assert!(cache.iter().count()<=cache.len());
+}
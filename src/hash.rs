@@ -1,14 +1,166 @@
 use crate::hash_str::HashStr;
+#[cfg(feature="std")]
 use std::collections::{HashMap,HashSet};
-use core::hash::{BuildHasher,BuildHasherDefault,Hash,Hasher};
+#[cfg(feature="std")]
+use core::hash::BuildHasherDefault;
+#[cfg(feature="std")]
+use alloc::{boxed::Box,sync::Arc};
+use core::hash::{Hash,Hasher};
 
-pub(crate) fn make_hash(value:&str)->u64{
-	let not_random_state=ahash::RandomState::with_seeds(0,0,0,0);
-	let mut hasher=not_random_state.build_hasher();
+// The same hash is used at compile time and at run time,
+// so HashStrs created by hstr! can be mixed with interned HashStrs.
+#[inline]
+pub(crate) const fn make_hash(value:&str)->u64{
+	const_hash(value)
+}
+
+// === Const Hash ===
+// A const fn reimplementation of the ahash fallback hasher using
+// the seeds from ahash::RandomState::with_seeds(0,0,0,0).
+// This does not depend on ahash or a proc macro, and is
+// consistent across targets regardless of AES support.
+
+const MULTIPLE:u64=6364136223846793005;
+const ROT:u32=23;
+// RandomState::with_seeds(0,0,0,0)
+const PI2:[u64;4]=[
+	0x4528_21e6_38d0_1377,
+	0xbe54_66cf_34e9_0c6c,
+	0xc0ac_29b7_c97c_50dd,
+	0x3f84_d5b5_b547_0917,
+];
+
+/// The target dependent parts of the hash.  The hash for a different
+/// target can be calculated, for example when generating code in a build script.
+#[derive(Clone,Copy,Debug)]
+pub(crate) struct HashTarget{
+	pub(crate) wide_multiply:bool,
+	pub(crate) big_endian:bool,
+}
+impl HashTarget{
+	// ahash picks the folded multiply based on the target architecture
+	#[cfg(feature="codegen")]
+	pub(crate) const WIDE_MULTIPLY_ARCHS:[&str;5]=["x86_64","aarch64","mips64","powerpc64","s390x"];
+	pub(crate) const NATIVE:Self=Self{
+		wide_multiply:cfg!(any(
+			target_arch="x86_64",
+			target_arch="aarch64",
+			target_arch="mips64",
+			target_arch="powerpc64",
+			target_arch="s390x",
+		)),
+		big_endian:cfg!(target_endian="big"),
+	};
+	/// Identifies the hash in saved data: 1 for the wide folded multiply, 2 for the narrow one.
+	pub(crate) const fn hash_id(self)->u32{
+		if self.wide_multiply{1}else{2}
+	}
+}
+
+#[inline]
+const fn folded_multiply(s:u64,by:u64,wide:bool)->u64{
+	if wide{
+		let result=(s as u128).wrapping_mul(by as u128);
+		((result&0xffff_ffff_ffff_ffff) as u64)^((result>>64) as u64)
+	}else{
+		let b1=s.wrapping_mul(by.swap_bytes());
+		let b2=s.swap_bytes().wrapping_mul(!by);
+		b1^b2.swap_bytes()
+	}
+}
+
+macro_rules! const_read{
+	($name:ident,$ty:ty)=>{
+		#[inline]
+		const fn $name(bytes:&[u8],at:usize,big_endian:bool)->u64{
+			let mut buf=[0;core::mem::size_of::<$ty>()];
+			let mut i=0;
+			while i<buf.len(){
+				buf[i]=bytes[at+i];
+				i+=1;
+			}
+			if big_endian{
+				<$ty>::from_be_bytes(buf) as u64
+			}else{
+				<$ty>::from_le_bytes(buf) as u64
+			}
+		}
+	};
+}
+const_read!(read_u16,u16);
+const_read!(read_u32,u32);
+const_read!(read_u64,u64);
+
+struct ConstHasher{
+	buffer:u64,
+	pad:u64,
+	extra_keys:[u64;2],
+	target:HashTarget,
+}
+impl ConstHasher{
+	#[inline]
+	const fn large_update(&mut self,a:u64,b:u64){
+		let combined=folded_multiply(a^self.extra_keys[0],b^self.extra_keys[1],self.target.wide_multiply);
+		self.buffer=(self.buffer.wrapping_add(self.pad)^combined).rotate_left(ROT);
+	}
+	const fn write(&mut self,data:&[u8]){
+		let be=self.target.big_endian;
+		let len=data.len();
+		self.buffer=self.buffer.wrapping_add(len as u64).wrapping_mul(MULTIPLE);
+		if 8<len{
+			if 16<len{
+				self.large_update(read_u64(data,len-16,be),read_u64(data,len-8,be));
+				let mut at=0;
+				while 16<len-at{
+					self.large_update(read_u64(data,at,be),read_u64(data,at+8,be));
+					at+=16;
+				}
+			}else{
+				self.large_update(read_u64(data,0,be),read_u64(data,len-8,be));
+			}
+		}else if 4<=len{
+			self.large_update(read_u32(data,0,be),read_u32(data,len-4,be));
+		}else if 2<=len{
+			self.large_update(read_u16(data,0,be),data[len-1] as u64);
+		}else if 1==len{
+			self.large_update(data[0] as u64,data[0] as u64);
+		}else{
+			self.large_update(0,0);
+		}
+	}
+	#[inline]
+	const fn finish(&self)->u64{
+		let rot=(self.buffer&63) as u32;
+		folded_multiply(self.buffer,self.pad,self.target.wide_multiply).rotate_left(rot)
+	}
+}
+
+/// Hash a str as it would be hashed on the given target.
+pub(crate) const fn hash_for_target(value:&str,target:HashTarget)->u64{
+	let mut hasher=ConstHasher{
+		buffer:PI2[1],
+		pad:PI2[0],
+		extra_keys:[PI2[2],PI2[3]],
+		target,
+	};
 	hasher.write(value.as_bytes());
 	hasher.finish()
 }
 
+/// Hash a str at compile time.  This is the same hash that is
+/// precomputed for every HashStr.
+///
+/// Usable in const and static items and generic const contexts:
+/// ```rust
+/// trait Named{
+///     const NAME:&'static str;
+///     const NAME_HASH:u64=hash_str::const_hash(Self::NAME);
+/// }
+/// ```
+pub const fn const_hash(value:&str)->u64{
+	hash_for_target(value,HashTarget::NATIVE)
+}
+
 // Just feed the precomputed hash into the Hasher. Note that this will of course
 // be terrible unless the Hasher in question is expecting a precomputed hash.
 impl Hash for HashStr{
@@ -18,13 +170,50 @@ impl Hash for HashStr{
     }
 }
 
-/// A standard `HashMap` using `&HashStr` as the key type with a custom `Hasher`
-/// that just uses the precomputed hash for speed instead of calculating it.
-pub type HashStrMap<'a,V>=HashMap<&'a HashStr,V,BuildHasherDefault<IdentityHasher>>;
+/// A standard `HashMap` owning its `Box<HashStr>` keys, for maps which outlive
+/// the cache the keys came from.  Query it with `&HashStr` or `&UnhashedStr`.
+#[cfg(feature="std")]
+pub type BoxHashStrMap<V>=HashMap<Box<HashStr>,V,BuildHasherDefault<IdentityHasher>>;
+
+/// A standard `HashSet` owning its `Box<HashStr>` keys.
+#[cfg(feature="std")]
+pub type BoxHashStrSet=HashSet<Box<HashStr>,BuildHasherDefault<IdentityHasher>>;
+
+/// A standard `HashMap` sharing its `Arc<HashStr>` keys, so they can be
+/// cheaply cloned into other maps.  Query it with `&HashStr` or `&UnhashedStr`.
+#[cfg(feature="std")]
+pub type ArcHashStrMap<V>=HashMap<Arc<HashStr>,V,BuildHasherDefault<IdentityHasher>>;
+
+/// A standard `HashSet` sharing its `Arc<HashStr>` keys.
+#[cfg(feature="std")]
+pub type ArcHashStrSet=HashSet<Arc<HashStr>,BuildHasherDefault<IdentityHasher>>;
 
-/// A standard `HashSet` using `&HashStr` as the key type with a custom `Hasher`
-/// that just uses the precomputed hash for speed instead of calculating it.
-pub type HashStrSet<'a>=HashSet<&'a HashStr,BuildHasherDefault<IdentityHasher>>;
+// Number of bins (shards) for concurrent maps
+#[cfg(any(feature="global",feature="concurrent"))]
+const BIN_SHIFT: usize = 6;
+#[cfg(any(feature="global",feature="concurrent"))]
+pub(crate) const NUM_BINS: usize = 1 << BIN_SHIFT;
+// Shift for top bits to determine bin a hash falls into
+#[cfg(feature="global")]
+const TOP_SHIFT: usize =
+    8 * core::mem::size_of::<usize>() - BIN_SHIFT;
+
+// Use the top bits of the hash to choose a bin
+#[cfg(feature="global")]
+#[inline]
+pub(crate) fn whichbin(hash: u64) -> usize {
+    ((hash >> TOP_SHIFT as u64) % NUM_BINS as u64) as usize
+}
+
+// Choose a shard for tables indexed by the precomputed hash from bits which
+// the tables do not use.  hashbrown indexes buckets with the low bits and tags
+// them with the top 7 bits, so shards chosen from either would leave every key
+// in a shard with the same bits.
+#[cfg(any(feature="global",feature="concurrent"))]
+#[inline]
+pub(crate) fn whichshard(hash: u64) -> usize {
+    ((hash >> 32) % NUM_BINS as u64) as usize
+}
 
 /// The worst hasher in the world -- the identity hasher.
 #[doc(hidden)]
@@ -48,6 +237,27 @@ impl Hasher for IdentityHasher {
     }
 }
 
+#[test]
+// ahash uses a different hasher when AES is available
+#[cfg(not(target_feature="aes"))]
+fn test_const_hash(){
+	use core::hash::BuildHasher;
+	fn ahash(value:&str)->u64{
+		let not_random_state=ahash::RandomState::with_seeds(0,0,0,0);
+		let mut hasher=not_random_state.build_hasher();
+		hasher.write(value.as_bytes());
+		hasher.finish()
+	}
+	// cover every length branch
+	let str="the quick brown fox jumps over the lazy dog";
+	for end in 0..=str.len(){
+		assert_eq!(const_hash(&str[..end]),ahash(&str[..end]));
+	}
+	const HASH:u64=const_hash("hey");
+	assert_eq!(HASH,make_hash("hey"));
+}
+
+#[cfg(feature="std")]
 #[test]
 fn test_hashing() {
 	let u1=&*HashStr::anonymous("the quick brown fox".to_owned());
@@ -61,10 +271,34 @@ fn test_hashing() {
 	u2.hash(&mut hasher);
 	assert_eq!(hasher.finish(), u2.precomputed_hash());
 
-	let mut hm = HashStrMap::<u32>::default();
+	let mut hm = crate::collections::HashStrMap::<u32>::default();
 	hm.insert(u1, 17);
 	hm.insert(u2, 42);
 
 	assert_eq!(hm.get(u1), Some(&17));
 	assert_eq!(hm.get(u2), Some(&42));
 }
+
+#[cfg(feature="std")]
+#[test]
+fn test_owned_keys(){
+	use crate::UnhashedStr;
+	let hs=&*HashStr::anonymous("owned".to_owned());
+
+	let mut boxed=BoxHashStrMap::<u32>::default();
+	boxed.insert(hs.into(),1);
+	assert_eq!(boxed.get(hs),Some(&1));
+	assert_eq!(boxed.get(UnhashedStr::from_ref("owned")),Some(&1));
+	let key=boxed.keys().next().unwrap().clone();
+	assert_eq!(key.precomputed_hash(),hs.precomputed_hash());
+
+	let mut shared=ArcHashStrMap::<u32>::default();
+	let arc:Arc<HashStr>=hs.into();
+	shared.insert(arc.clone(),2);
+	let mut set=ArcHashStrSet::default();
+	set.insert(arc);
+	assert_eq!(shared.get(hs),Some(&2));
+	assert_eq!(shared.get(UnhashedStr::from_ref("owned")),Some(&2));
+	assert!(set.contains(UnhashedStr::from_ref("owned")));
+	assert!(!set.contains(UnhashedStr::from_ref("other")));
+}
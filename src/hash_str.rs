@@ -1,4 +1,6 @@
 use crate::hash::make_hash;
+use alloc::boxed::Box;
+use alloc::string::String;
 
 /// HashStr is a dynamically sized type so it is used similarly to &str.
 /// A hash is stored at the beginning followed by a str.  The length is
@@ -17,12 +19,22 @@ pub enum RefFromBytesError{
 	TooShort,
 	UTF8(core::str::Utf8Error),
 }
-impl std::fmt::Display for RefFromBytesError{
-	fn fmt(&self,f:&mut std::fmt::Formatter<'_>)->std::fmt::Result{
+impl core::fmt::Display for RefFromBytesError{
+	fn fmt(&self,f:&mut core::fmt::Formatter<'_>)->core::fmt::Result{
 		write!(f,"{self:?}")
 	}
 }
 
+/// The string does not match any variant of the enum.
+/// Returned by `TryFrom<&HashStr>` implementations generated by `#[derive(HashStrEnum)]`.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct UnknownVariantError;
+impl core::fmt::Display for UnknownVariantError{
+	fn fmt(&self,f:&mut core::fmt::Formatter<'_>)->core::fmt::Result{
+		write!(f,"unknown variant")
+	}
+}
+
 impl HashStr{
 	#[inline]
 	pub const fn precomputed_hash(&self)->u64{
@@ -36,7 +48,7 @@ impl HashStr{
 	}
 	/// Struct bytes including hash prefix and trailing str
 	#[inline]
-	pub const fn as_hash_str_bytes<'a>(&'a self)->&'a [u8]{
+	pub const fn as_hash_str_bytes(&self)->&[u8]{
 		// SAFETY: HashStr is always valid as bytes,
 		// but the fat pointer must be widened to undo the hack
 		unsafe{core::slice::from_raw_parts(
@@ -46,7 +58,7 @@ impl HashStr{
 	}
 	/// Create a `&HashStr` from bytes.
 	#[inline]
-	pub fn ref_from_bytes<'a>(bytes:&'a [u8])->Result<&'a Self,RefFromBytesError>{
+	pub fn ref_from_bytes(bytes:&[u8])->Result<&Self,RefFromBytesError>{
 		// check len
 		let Some(str_slice)=bytes.get(SIZE_HASH..)else{
 			return Err(RefFromBytesError::TooShort);
@@ -63,11 +75,11 @@ impl HashStr{
 	}
 	/// Create a `&HashStr` from bytes.
 	///
-	/// SAFETY:
+	/// # Safety
 	/// - `bytes.len()` must be at least 8
 	/// - `&bytes[8..]` must be valid UTF-8
 	#[inline]
-	pub const unsafe fn ref_from_bytes_unchecked<'a>(bytes:&'a [u8])->&'a Self{
+	pub const unsafe fn ref_from_bytes_unchecked(bytes:&[u8])->&Self{
 		// adapted from https://github.com/jonhoo/codecrafters-bittorrent-rust/blob/9dc424d4699febed87fefe8eef94509ab5392b56/src/peer.rs#L350-L359
 		let ptr=bytes as *const [u8] as *const u8;
 		// fat pointer hack: set size to the str portion without the hash
@@ -83,30 +95,44 @@ impl HashStr{
 	/// An anonymous HashStr that is not owned by a StringCache
 	#[inline]
 	pub fn anonymous(value:String)->Box<HashStr>{
-		let hash=make_hash(&value);
-		let mut bytes=value.into_bytes();
-		// prefix bytes with hash
-		bytes.reserve_exact(SIZE_HASH);
-		insert_bytes(&mut bytes,&hash.to_ne_bytes());
-
-		let boxed=bytes.into_boxed_slice();
-		// SAFETY: leak the box to avoid calling its destructor
-		let href=unsafe{Self::ref_from_bytes_unchecked(Box::leak(boxed))};
-		// SAFETY: we know that this is a unique reference because we just created it
+		Self::boxed_str_with_hash(make_hash(&value),&value)
+	}
+	/// Allocate a new Box<HashStr> with a known hash.
+	#[inline]
+	pub(crate) fn boxed_str_with_hash(hash:u64,str:&str)->Box<HashStr>{
+		let bytes=alloc_hash_str_bytes(SIZE_HASH+str.len());
+		bytes[..SIZE_HASH].copy_from_slice(&hash.to_ne_bytes());
+		bytes[SIZE_HASH..].copy_from_slice(str.as_bytes());
+		// SAFETY: bytes is an allocation with the layout of a HashStr,
+		// containing a hash followed by a valid str
+		unsafe{Self::box_from_alloc(bytes)}
+	}
+	/// # Safety
+	/// - `bytes` must be allocated with `hash_str_layout(bytes.len())`
+	/// - `bytes` must be a hash followed by a valid str
+	#[inline]
+	pub(crate) unsafe fn box_from_alloc(bytes:&mut [u8])->Box<HashStr>{
+		let href=unsafe{Self::ref_from_bytes_unchecked(bytes)};
+		// SAFETY: the allocation is unique and has the size and alignment of the HashStr
 		unsafe{Box::from_raw(href as *const Self as *mut Self)}
 	}
 }
 
-// Copied from std String
-// Why doesn't this function exist on std Vec?
-fn insert_bytes(vec:&mut Vec<u8>, bytes: &[u8]) {
-    let len = vec.len();
-    let amt = bytes.len();
-    vec.reserve_exact(amt);
+/// The layout of a HashStr occupying `len` bytes including the hash,
+/// which is aligned to the hash and padded to the alignment.
+#[inline]
+pub(crate) fn hash_str_layout(len:usize)->alloc::alloc::Layout{
+	alloc::alloc::Layout::from_size_align(len,SIZE_HASH).unwrap().pad_to_align()
+}
 
-    unsafe {
-        core::ptr::copy(vec.as_ptr(), vec.as_mut_ptr().add(amt), len);
-        core::ptr::copy_nonoverlapping(bytes.as_ptr(), vec.as_mut_ptr(), amt);
-        vec.set_len(len + amt);
-    }
+/// Allocate zeroed bytes with the layout of a HashStr.  Must be freed as a `Box<HashStr>`.
+fn alloc_hash_str_bytes<'a>(len:usize)->&'a mut [u8]{
+	let layout=hash_str_layout(len);
+	// SAFETY: the layout is at least SIZE_HASH bytes
+	let ptr=unsafe{alloc::alloc::alloc_zeroed(layout)};
+	if ptr.is_null(){
+		alloc::alloc::handle_alloc_error(layout);
+	}
+	// SAFETY: ptr is a non-null allocation of at least len bytes
+	unsafe{core::slice::from_raw_parts_mut(ptr,len)}
 }
@@ -0,0 +1,212 @@
+use crate::hash::const_hash;
+use crate::hash_str::{HashStr,SIZE_HASH};
+
+// Compile time word lists.  Each non-empty line of the text is an entry.
+// The entries are written into a single blob with each HashStr padded to
+// the alignment of the hash, then the blob is sliced into HashStrs.
+
+/// The next non-empty line at or after `pos` with surrounding
+/// whitespace removed, and the position after it.
+const fn next_line(text:&[u8],mut pos:usize)->Option<(&[u8],usize)>{
+	while pos<text.len(){
+		let mut end=pos;
+		while end<text.len()&&text[end]!=b'\n'{
+			end+=1;
+		}
+		let line=text.split_at(end).0.split_at(pos).1.trim_ascii();
+		if !line.is_empty(){
+			return Some((line,end+1));
+		}
+		pos=end+1;
+	}
+	None
+}
+
+const fn line_str(line:&[u8])->&str{
+	match core::str::from_utf8(line){
+		Ok(str)=>str,
+		Err(_)=>panic!("invalid utf8"),
+	}
+}
+
+/// Size of a HashStr in the blob, including padding.
+#[inline]
+const fn padded_size(len:usize)->usize{
+	(SIZE_HASH+len).next_multiple_of(SIZE_HASH)
+}
+
+/// The number of entries in the text.
+#[doc(hidden)]
+pub const fn line_count(text:&str)->usize{
+	let text=text.as_bytes();
+	let mut count=0;
+	let mut pos=0;
+	while let Some((_,next))=next_line(text,pos){
+		count+=1;
+		pos=next;
+	}
+	count
+}
+
+/// The size of the blob for the entries in the text.
+#[doc(hidden)]
+pub const fn lines_blob_len(text:&str)->usize{
+	let text=text.as_bytes();
+	let mut len=0;
+	let mut pos=0;
+	while let Some((line,next))=next_line(text,pos){
+		len+=padded_size(line.len());
+		pos=next;
+	}
+	len
+}
+
+/// Write the entries in the text into a blob of hash prefixed strings.
+#[doc(hidden)]
+pub const fn lines_blob<const LEN:usize>(text:&str)->[u8;LEN]{
+	let text=text.as_bytes();
+	let mut blob=[0;LEN];
+	let mut offset=0;
+	let mut pos=0;
+	while let Some((line,next))=next_line(text,pos){
+		let hash_bytes=const_hash(line_str(line)).to_ne_bytes();
+		let mut i=0;
+		while i<SIZE_HASH{
+			blob[offset+i]=hash_bytes[i];
+			i+=1;
+		}
+		let mut i=0;
+		while i<line.len(){
+			blob[offset+SIZE_HASH+i]=line[i];
+			i+=1;
+		}
+		offset+=padded_size(line.len());
+		pos=next;
+	}
+	blob
+}
+
+/// Slice a blob written by `lines_blob` into HashStrs.
+///
+/// # Safety
+/// - `blob` must be written by `lines_blob` from the same text
+/// - `blob` must be aligned to the hash
+#[doc(hidden)]
+pub const unsafe fn lines_hash_strs<const N:usize>(text:&str,blob:&'static [u8])->[&'static HashStr;N]{
+	let text=text.as_bytes();
+	let mut hash_strs=[crate::hstr!("");N];
+	let mut offset=0;
+	let mut pos=0;
+	let mut i=0;
+	while let Some((line,next))=next_line(text,pos){
+		let bytes=blob.split_at(offset).1.split_at(SIZE_HASH+line.len()).0;
+		// SAFETY: each entry is a hash followed by the str of a line,
+		// padded so that the next entry is also aligned
+		hash_strs[i]=unsafe{HashStr::ref_from_bytes_unchecked(bytes)};
+		offset+=padded_size(line.len());
+		pos=next;
+		i+=1;
+	}
+	assert!(i==N,"wrong number of lines");
+	hash_strs
+}
+
+/// Read a file at compile time and construct a `&'static [&'static HashStr]`
+/// with one HashStr for each non-empty line of the file, in file order.
+/// Whitespace around each line is removed.  The path is resolved like `include_str!`.
+///
+/// ```rust
+/// use hash_str::{hstr,include_hstrs,HashStr};
+///
+/// static WORDS:&[&HashStr]=include_hstrs!(concat!(env!("CARGO_MANIFEST_DIR"),"/tests/words.txt"));
+/// assert_eq!(WORDS[0],hstr!("a"));
+/// ```
+#[macro_export]
+macro_rules! include_hstrs{
+	($path:expr)=>{
+		{
+			// the only item visible to the path expression
+			const TEXT:&str=::core::include_str!($path);
+			{
+				const N:usize=$crate::line_count(TEXT);
+				const LEN:usize=$crate::lines_blob_len(TEXT);
+				#[repr(C,align(8))]
+				struct Aligned([u8;LEN]);
+				const BLOB:Aligned=Aligned($crate::lines_blob(TEXT));
+				const ARRAY:[&$crate::HashStr;N]=unsafe{$crate::lines_hash_strs(TEXT,&BLOB.0)};
+				&ARRAY
+			}
+		}
+	};
+}
+
+/// Read a file at compile time and construct a `StaticHashStrSet` of the
+/// non-empty lines of the file, like `include_hstrs!`.
+/// Duplicate lines are a compile error.
+///
+/// ```rust
+/// use hash_str::{hstr,include_hstr_set,StaticHashStrSet};
+///
+/// static STOP_WORDS:StaticHashStrSet=include_hstr_set!(concat!(env!("CARGO_MANIFEST_DIR"),"/tests/words.txt"));
+/// assert!(STOP_WORDS.contains(hstr!("the")));
+/// assert!(!STOP_WORDS.contains("bruh"));
+/// ```
+#[macro_export]
+macro_rules! include_hstr_set{
+	($path:expr)=>{
+		{
+			const KEYS:&[&$crate::HashStr]=$crate::include_hstrs!($path);
+			const N:usize=KEYS.len();
+			const B:usize=$crate::phf_bucket_count(N);
+			const S:usize=$crate::phf_slot_count(N);
+			const PHF:$crate::Phf<N,B,S>=$crate::Phf::build(KEYS);
+			$crate::StaticHashStrSet{
+				index:$crate::PhfIndex{
+					seed:PHF.seed,
+					disps:&PHF.disps,
+					map:&PHF.map,
+				},
+				keys:KEYS,
+			}
+		}
+	};
+}
+
+#[test]
+fn test_include_hstrs(){
+	use crate::hstr;
+	use crate::phf::StaticHashStrSet;
+	static WORDS:&[&HashStr]=crate::include_hstrs!("../tests/words.txt");
+	let text=include_str!("../tests/words.txt");
+	let lines:Vec<&str>=text.lines().map(str::trim).filter(|line|!line.is_empty()).collect();
+	assert_eq!(WORDS,lines);
+	for &word in WORDS{
+		assert_eq!(word,&*HashStr::anonymous(word.as_str().to_owned()));
+		assert_eq!(word.as_hash_str_bytes().as_ptr().align_offset(SIZE_HASH),0);
+	}
+
+	static SET:StaticHashStrSet=crate::include_hstr_set!("../tests/words.txt");
+	assert_eq!(SET.len(),lines.len());
+	for line in lines{
+		assert!(SET.contains(line));
+	}
+	assert!(SET.contains(hstr!("the")));
+	assert!(!SET.contains(hstr!("bruh")));
+
+	assert_eq!(line_count(""),0);
+	assert_eq!(line_count("\n \r\n"),0);
+	assert_eq!(line_count("a\r\n\nb"),2);
+}
+
+#[test]
+fn test_include_hstr_set_large(){
+	use crate::phf::StaticHashStrSet;
+	static SET:StaticHashStrSet=crate::include_hstr_set!("../tests/large_words.txt");
+	let text=include_str!("../tests/large_words.txt");
+	assert_eq!(SET.len(),1200);
+	for (word,line) in SET.iter().zip(text.lines()){
+		assert_eq!(word.as_str(),line);
+		assert_eq!(SET.get(line),Some(word));
+	}
+	assert!(!SET.contains("bruh"));
+}
@@ -0,0 +1,42 @@
+use crate::hash::IdentityHasher;
+use crate::hash_str::HashStr;
+use core::hash::BuildHasherDefault;
+use indexmap::{IndexMap,IndexSet};
+
+/// An `IndexMap` using `&HashStr` as the key type with a custom `Hasher`
+/// that just uses the precomputed hash, iterating in insertion order.
+/// Index it with `&HashStr`, `&UnhashedStr` or `&HashedStr`.
+///
+/// ```rust
+/// use hash_str::{hstr,HashedStr,HashStrIndexMap};
+///
+/// let mut map=HashStrIndexMap::default();
+/// map.insert(hstr!("b"),1);
+/// map.insert(hstr!("a"),2);
+/// assert_eq!(map.keys().copied().collect::<Vec<_>>(),[hstr!("b"),hstr!("a")]);
+/// assert_eq!(map.get(&HashedStr::new("a")),Some(&2));
+/// ```
+pub type HashStrIndexMap<'a,V>=IndexMap<&'a HashStr,V,BuildHasherDefault<IdentityHasher>>;
+
+/// An `IndexSet` using `&HashStr` as the key type with a custom `Hasher`
+/// that just uses the precomputed hash, iterating in insertion order.
+pub type HashStrIndexSet<'a>=IndexSet<&'a HashStr,BuildHasherDefault<IdentityHasher>>;
+
+#[test]
+fn test_index_map(){
+	use crate::ornaments::{HashedStr,UnhashedStr};
+	let words=["c","a","b"].map(|s|HashStr::anonymous(s.into()));
+	let mut map=HashStrIndexMap::default();
+	for (i,word) in words.iter().enumerate(){
+		map.insert(&**word,i);
+	}
+	assert!(map.keys().map(|k|k.as_str()).eq(["c","a","b"]));
+	assert_eq!(map.get(&HashedStr::new("a")),Some(&1));
+	assert_eq!(map.get(UnhashedStr::from_ref("b")),Some(&2));
+	assert_eq!(map.get_index_of(&HashedStr::new("b")),Some(2));
+	assert_eq!(map.get(&HashedStr::new("d")),None);
+
+	let set:HashStrIndexSet=words.iter().rev().map(|word|&**word).collect();
+	assert!(set.iter().map(|k|k.as_str()).eq(["b","a","c"]));
+	assert!(set.contains(&HashedStr::new("c")));
+}
@@ -1,22 +1,68 @@
+#![cfg_attr(not(any(test,feature="std")),no_std)]
+extern crate alloc;
+
+#[cfg(feature="derive")]
+pub use hash_str_derive::HashStrEnum;
+
 mod hash;
 pub use hash::*;
+#[cfg(feature="std")]
+mod collections;
+#[cfg(feature="std")]
+pub use collections::*;
 mod hash_str;
 pub use hash_str::*;
+mod builder;
+pub use builder::*;
 mod macros;
-pub use macros::*;
+mod small;
+pub use small::*;
+mod phf;
+pub use phf::*;
+mod include;
+pub use include::*;
+mod table;
+pub use table::*;
 
 #[cfg(feature="cache")]
 mod cache;
 #[cfg(feature="cache")]
 pub use cache::*;
+#[cfg(feature="cache")]
+mod map;
+#[cfg(feature="cache")]
+pub use map::*;
 
 #[cfg(feature="global")]
 mod global;
 #[cfg(feature="global")]
 pub use global::*;
 
+#[cfg(feature="global")]
+mod pool;
+#[cfg(feature="global")]
+pub use pool::*;
+
+#[cfg(feature="concurrent")]
+mod concurrent;
+#[cfg(feature="concurrent")]
+pub use concurrent::*;
+
+#[cfg(all(feature="rayon",feature="cache"))]
+mod par;
+#[cfg(all(feature="rayon",feature="cache"))]
+pub use par::*;
+
 #[cfg(feature="serde")]
 pub mod serde;
 
+#[cfg(feature="codegen")]
+pub mod codegen;
+
+#[cfg(feature="indexmap")]
+mod index_map;
+#[cfg(feature="indexmap")]
+pub use index_map::*;
+
 mod ornaments;
 pub use ornaments::*;
@@ -1,32 +1,180 @@
-pub use ahash_macro::hash_literal;
+/// Compute the hash of a `&'static str` const expression at compile time.
+/// Always matches `const_hash` and the runtime hash of the same string.
+///
+/// ```rust
+/// const HASH:u64=hash_str::hash_literal!("hey");
+/// assert_eq!(HASH,hash_str::const_hash("hey"));
+/// ```
+#[macro_export]
+macro_rules! hash_literal{
+	($str:expr)=>{
+		{
+			const HASH:u64=$crate::const_hash($str);
+			HASH
+		}
+	};
+}
 
 /// Construct a &'static HashStr at compile time.  These are presumably deduplicated by the compiler.
+/// Accepts any `&'static str` const expression such as a literal, a const, or `concat!`.
+///
+/// ```rust
+/// use hash_str::{hstr,HashStr};
+///
+/// const PREFIX:&str="hash";
+/// const NAME:&HashStr=hstr!(concat!("hash","_str"));
+/// assert_eq!(hstr!(PREFIX).as_str(),"hash");
+/// assert_eq!(NAME.as_str(),"hash_str");
+/// ```
 #[macro_export]
 macro_rules! hstr{
-	($str:literal)=>{
+	($str:expr)=>{
+		{
+			// the only item visible to the input expression
+			const HSTR_INPUT:&str=$str;
+			{
+				use $crate::{HashStr,SIZE_HASH};
+				const SIZE:usize=SIZE_HASH+HSTR_INPUT.len();
+				// HashStr is aligned to its hash, and its size is padded to the alignment
+				#[repr(C,align(8))]
+				struct Aligned([u8;SIZE.next_multiple_of(SIZE_HASH)]);
+				const BYTES:Aligned={
+					let mut bytes=[0;SIZE.next_multiple_of(SIZE_HASH)];
+					let hash=$crate::const_hash(HSTR_INPUT);
+					let hash_bytes=hash.to_ne_bytes();
+					let mut i=0;
+					while i<SIZE_HASH{
+						bytes[i]=hash_bytes[i];
+						i+=1;
+					}
+					let str_bytes=HSTR_INPUT.as_bytes();
+					while i<SIZE{
+						bytes[i]=str_bytes[i-SIZE_HASH];
+						i+=1;
+					}
+					Aligned(bytes)
+				};
+				unsafe{HashStr::ref_from_bytes_unchecked(BYTES.0.split_at(SIZE).0)}
+			}
+		}
+	};
+}
+
+/// Construct a `&'static [&'static HashStr]` at compile time from a list of
+/// const str expressions, in declaration order.
+/// Two entries with the same string or hash are a compile error.
+///
+/// ```rust
+/// use hash_str::{hstr,hstr_array,HashStr};
+///
+/// static KEYWORDS:&[&HashStr]=hstr_array!["if","else","while"];
+/// assert_eq!(KEYWORDS.len(),3);
+/// assert!(KEYWORDS.contains(&hstr!("else")));
+/// ```
+///
+/// ```rust,compile_fail
+/// let array=hash_str::hstr_array!["if","if"];
+/// ```
+#[macro_export]
+macro_rules! hstr_array{
+	($($str:expr),* $(,)?)=>{
+		{
+			const ARRAY:&[&$crate::HashStr]=&[$($crate::hstr!($str)),*];
+			const _:()=$crate::assert_unique_hash_strs(ARRAY);
+			ARRAY
+		}
+	};
+}
+
+/// Like `hstr_array!`, but the HashStrs are sorted by string at compile time
+/// so the slice can be binary searched by string.
+///
+/// ```rust
+/// use hash_str::{hstr_sorted_array,HashStr};
+///
+/// static STOP_WORDS:&[&HashStr]=hstr_sorted_array!["the","a","of","and"];
+/// assert_eq!(STOP_WORDS,["a","and","of","the"]);
+/// assert!(STOP_WORDS.binary_search_by(|probe|probe.as_str().cmp("of")).is_ok());
+/// ```
+#[macro_export]
+macro_rules! hstr_sorted_array{
+	($($str:expr),* $(,)?)=>{
+		{
+			const ARRAY:&[&$crate::HashStr]=&[$($crate::hstr!($str)),*];
+			const SORTED:[&$crate::HashStr;ARRAY.len()]=$crate::sort_hash_strs(ARRAY);
+			const _:()=$crate::assert_unique_hash_strs(&SORTED);
+			&SORTED
+		}
+	};
+}
+
+/// Match a string against string literals by dispatching on the precomputed hash,
+/// then confirming the match with a string compare.  The value can be anything
+/// implementing `GetHash+AsRef<str>` such as `&HashStr` or `HashedStr`.
+/// Arms are separated by commas, and the `_` arm is required.
+/// Two arms with the same string or hash are a compile error.
+///
+/// ```rust
+/// use hash_str::{hstr,hstr_match};
+///
+/// let method=hstr!("post");
+/// let n=hstr_match!(method{
+///     "get"|"head"=>1,
+///     "post"=>2,
+///     _=>0,
+/// });
+/// assert_eq!(n,2);
+/// ```
+///
+/// ```rust,compile_fail
+/// let n=hash_str::hstr_match!("get"{
+///     "get"=>1,
+///     "get"=>2,
+///     _=>0,
+/// });
+/// ```
+#[macro_export]
+macro_rules! hstr_match{
+	(@arms [$($value:tt)+] {$($($str:literal)|+ =>$arm:expr,)* _=>$default:expr $(,)?})=>{
 		{
-			use $crate::hash_literal;
-			use $crate::{HashStr,SIZE_HASH};
-			const SIZE:usize=SIZE_HASH+$str.len();
-			const BYTES:[u8;SIZE]={
-				let mut bytes=[0;SIZE];
-				let hash=hash_literal!($str);
-				let hash_bytes=hash.to_ne_bytes();
-				let mut i=0;
-				while i<SIZE_HASH{
-					bytes[i]=hash_bytes[i];
-					i+=1;
-				}
-				let str_bytes=$str.as_bytes();
-				while i<SIZE{
-					bytes[i]=str_bytes[i-SIZE_HASH];
-					i+=1;
-				}
-				bytes
-			};
-			unsafe{HashStr::ref_from_bytes_unchecked(&BYTES)}
+			// hashed by the const hash so the hashes match the target like hstr!
+			const _:()=$crate::assert_unique_hashes(&[$($($crate::const_hash($str)),+),*]);
+			let value=&$($value)+;
+			let str=::core::convert::AsRef::<str>::as_ref(value);
+			match $crate::GetHash::get_hash(value){
+				$(hash if ($(hash==const{$crate::const_hash($str)})||+)&&::core::matches!(str,$($str)|+)=>$arm,)*
+				_=>$default,
+			}
 		}
 	};
+	// munch tokens of the value until the arms are the only token left
+	(@value [$($value:tt)*] {$($arms:tt)*})=>{
+		$crate::hstr_match!(@arms [$($value)*] {$($arms)*})
+	};
+	(@value [$($value:tt)*] $next:tt $($rest:tt)+)=>{
+		$crate::hstr_match!(@value [$($value)* $next] $($rest)+)
+	};
+	($($tokens:tt)+)=>{
+		$crate::hstr_match!(@value [] $($tokens)+)
+	};
+}
+
+/// Intern a formatted string into the global cache, like `format!` but
+/// returning a `&'static HashStr`.  Does not allocate if the string is already present.
+///
+/// ```rust
+/// use hash_str::hformat;
+///
+/// let (module,name)=("std","fmt");
+/// let hs=hformat!("{module}::{name}");
+/// assert!(core::ptr::addr_eq(hs,hformat!("std::fmt")));
+/// ```
+#[cfg(feature="global")]
+#[macro_export]
+macro_rules! hformat{
+	($($arg:tt)*)=>{
+		$crate::get_cache().intern_fmt(format_args!($($arg)*))
+	};
 }
 
 #[cfg(test)]
@@ -34,10 +182,50 @@ mod test{
 	use crate::hash::make_hash;
 	use crate::hash_str::{HashStr,SIZE_HASH};
 	#[test]
-	fn ahash_macro(){
-		let hash_macro=ahash_macro::hash_literal!("hey");
+	fn hash_literal(){
+		let hash_macro=hash_literal!("hey");
 		let hash_runtime=make_hash("hey");
 		assert_eq!(hash_macro,hash_runtime);
+		assert_eq!(hash_literal!(concat!("h","ey")),hash_runtime);
+	}
+	#[test]
+	fn hstr_match(){
+		use crate::ornaments::HashedStr;
+		fn method(value:&HashStr)->u32{
+			hstr_match!(value{
+				"get"|"head"=>1,
+				"post"=>2,
+				_=>0,
+			})
+		}
+		assert_eq!(method(hstr!("get")),1);
+		assert_eq!(method(hstr!("head")),1);
+		assert_eq!(method(&HashStr::anonymous("post".to_owned())),2);
+		assert_eq!(method(hstr!("put")),0);
+		let hashed=HashedStr::new("post");
+		assert!(hstr_match!(hashed{"post"=>true,_=>false}));
+	}
+	#[test]
+	fn hstr_array(){
+		static ARRAY:&[&HashStr]=hstr_array!["b","c","a",concat!("a","b")];
+		assert_eq!(ARRAY,["b","c","a","ab"]);
+		assert!(core::ptr::addr_eq(ARRAY[0],hstr!("b")));
+		static SORTED:&[&HashStr]=hstr_sorted_array!["b","c","a",concat!("a","b")];
+		assert_eq!(SORTED,["a","ab","b","c"]);
+		for str in ["a","ab","b","c"]{
+			assert!(SORTED.binary_search_by(|probe|probe.as_str().cmp(str)).is_ok());
+		}
+		assert!(SORTED.binary_search_by(|probe|probe.as_str().cmp("d")).is_err());
+		let empty:&[&HashStr]=hstr_sorted_array![];
+		assert!(empty.is_empty());
+	}
+	#[test]
+	fn const_expr(){
+		const SIZE:&str="size";
+		assert_eq!(hstr!(SIZE),hstr!("size"));
+		assert_eq!(hstr!(concat!("a","b")),hstr!("ab"));
+		static STATIC:&HashStr=hstr!(concat!("hash","_","str"));
+		assert_eq!(STATIC.precomputed_hash(),make_hash("hash_str"));
 	}
 	#[test]
 	fn dedup(){
@@ -0,0 +1,457 @@
+use crate::cache::HashStrHost;
+use crate::hash::IdentityHasher;
+use crate::ornaments::{GetHash,HashedStr,UnhashedStr};
+use crate::hash_str::HashStr;
+use core::hash::BuildHasherDefault;
+use core::ops::Deref;
+use hashbrown::hash_table::{self,HashTable};
+
+/// A hashbrown `HashMap` using `&HashStr` as the key type with a custom `Hasher`
+/// that just uses the precomputed hash.  Lookups accept anything `Equivalent`
+/// to the key: `&HashStr`, `&HashedStr` and `&UnhashedStr`.
+///
+/// `UnhashedStr` hashes the str on the fly, and is `Equivalent` through the
+/// blanket impl over its `Borrow` impls, which a direct impl would conflict with.
+/// A plain `str` cannot be passed to `get`, because its `Hash` impl feeds the bytes
+/// to the hasher instead of the precomputed hash.  Use the `_str` methods of
+/// [`HashbrownHashStrMapExt`] instead, which wrap it with `UnhashedStr::from_ref`.
+///
+/// ```rust
+/// use hash_str::{hstr,HashedStr,HashbrownHashStrMap,HashbrownHashStrMapExt};
+///
+/// let mut map=HashbrownHashStrMap::default();
+/// map.insert(hstr!("bruh"),1);
+/// assert_eq!(map.get(&HashedStr::new("bruh")),Some(&1));
+/// assert_eq!(map.get_str("bruh"),Some(&1));
+/// ```
+pub type HashbrownHashStrMap<'a,V>=hashbrown::HashMap<&'a HashStr,V,BuildHasherDefault<IdentityHasher>>;
+
+/// A hashbrown `HashSet` using `&HashStr` as the key type with a custom `Hasher`
+/// that just uses the precomputed hash.  Look up a plain `str` with [`HashbrownHashStrSetExt`].
+pub type HashbrownHashStrSet<'a>=hashbrown::HashSet<&'a HashStr,BuildHasherDefault<IdentityHasher>>;
+
+/// Lookups by plain `str` for [`HashbrownHashStrMap`], hashing the str on the fly.
+pub trait HashbrownHashStrMapExt<'a,V>{
+	fn get_str(&self,key:&str)->Option<&V>;
+	fn get_str_mut(&mut self,key:&str)->Option<&mut V>;
+	fn get_key_value_str(&self,key:&str)->Option<(&'a HashStr,&V)>;
+	fn contains_str(&self,key:&str)->bool;
+	fn remove_str(&mut self,key:&str)->Option<V>;
+}
+impl<'a,V> HashbrownHashStrMapExt<'a,V> for HashbrownHashStrMap<'a,V>{
+	#[inline]
+	fn get_str(&self,key:&str)->Option<&V>{
+		self.get(UnhashedStr::from_ref(key))
+	}
+	#[inline]
+	fn get_str_mut(&mut self,key:&str)->Option<&mut V>{
+		self.get_mut(UnhashedStr::from_ref(key))
+	}
+	#[inline]
+	fn get_key_value_str(&self,key:&str)->Option<(&'a HashStr,&V)>{
+		self.get_key_value(UnhashedStr::from_ref(key)).map(|(&key,value)|(key,value))
+	}
+	#[inline]
+	fn contains_str(&self,key:&str)->bool{
+		self.contains_key(UnhashedStr::from_ref(key))
+	}
+	#[inline]
+	fn remove_str(&mut self,key:&str)->Option<V>{
+		self.remove(UnhashedStr::from_ref(key))
+	}
+}
+
+/// Lookups by plain `str` for [`HashbrownHashStrSet`], hashing the str on the fly.
+pub trait HashbrownHashStrSetExt<'a>{
+	fn get_str(&self,key:&str)->Option<&'a HashStr>;
+	fn contains_str(&self,key:&str)->bool;
+	fn remove_str(&mut self,key:&str)->bool;
+}
+impl<'a> HashbrownHashStrSetExt<'a> for HashbrownHashStrSet<'a>{
+	#[inline]
+	fn get_str(&self,key:&str)->Option<&'a HashStr>{
+		self.get(UnhashedStr::from_ref(key)).copied()
+	}
+	#[inline]
+	fn contains_str(&self,key:&str)->bool{
+		self.contains(UnhashedStr::from_ref(key))
+	}
+	#[inline]
+	fn remove_str(&mut self,key:&str)->bool{
+		self.remove(UnhashedStr::from_ref(key))
+	}
+}
+
+/// A map from HashStr keys to values which can be queried with a [`HashedStr`],
+/// reusing a precomputed hash without allocating a HashStr.
+/// The key type may be `&HashStr`, `Box<HashStr>` or `Arc<HashStr>`.
+///
+/// ```rust
+/// use hash_str::{HashedStr,HashedStrMap,HashStr};
+///
+/// let mut map=HashedStrMap::<Box<HashStr>,u32>::new();
+/// // hashes "bruh" once for both the lookup and the new key
+/// *map.entry("bruh").or_insert(0)+=1;
+/// *map.entry("bruh").or_insert(0)+=1;
+///
+/// let key=HashedStr::new("bruh");
+/// assert_eq!(map.get_hashed(key),Some(&2));
+/// ```
+#[derive(Debug,Clone)]
+pub struct HashedStrMap<K,V>{
+	entries:HashTable<(K,V)>,
+}
+
+pub(crate) fn get_key_hash<K:Deref<Target=HashStr>,V>((key,_):&(K,V))->u64{
+	key.precomputed_hash()
+}
+
+impl<K,V> HashedStrMap<K,V>{
+	#[inline]
+	pub fn new()->Self{
+		HashedStrMap{
+			entries:HashTable::new(),
+		}
+	}
+	#[inline]
+	pub fn with_capacity(capacity:usize)->Self{
+		HashedStrMap{
+			entries:HashTable::with_capacity(capacity),
+		}
+	}
+	#[inline]
+	pub fn clear(&mut self){
+		self.entries.clear();
+	}
+	#[inline]
+	pub fn len(&self)->usize{
+		self.entries.len()
+	}
+	#[inline]
+	pub fn is_empty(&self)->bool{
+		self.entries.is_empty()
+	}
+	#[inline]
+	pub fn capacity(&self)->usize{
+		self.entries.capacity()
+	}
+	#[inline]
+	pub fn iter(&self)->impl Iterator<Item=(&K,&V)>{
+		self.entries.iter().map(|(key,value)|(key,value))
+	}
+	#[inline]
+	pub fn iter_mut(&mut self)->impl Iterator<Item=(&K,&mut V)>{
+		self.entries.iter_mut().map(|(key,value)|(&*key,value))
+	}
+	#[inline]
+	pub fn keys(&self)->impl Iterator<Item=&K>{
+		self.entries.iter().map(|(key,_)|key)
+	}
+	#[inline]
+	pub fn values(&self)->impl Iterator<Item=&V>{
+		self.entries.iter().map(|(_,value)|value)
+	}
+}
+
+impl<K:Deref<Target=HashStr>,V> HashedStrMap<K,V>{
+	/// Fetch a value, utilizing the precalculated hash if possible.
+	#[inline]
+	pub fn get<'a>(&self,index:impl GetHash+Into<&'a str>)->Option<&V>{
+		self.get_hashed(HashedStr{hash:index.get_hash(),str:index.into()})
+	}
+	/// Fetch a value using the precomputed hash.
+	#[inline]
+	pub fn get_hashed(&self,index:HashedStr<'_>)->Option<&V>{
+		self.get_key_value_hashed(index).map(|(_,value)|value)
+	}
+	#[inline]
+	pub fn get_hashed_mut(&mut self,index:HashedStr<'_>)->Option<&mut V>{
+		self.entries.find_mut(index.hash,|(key,_)|key.as_str()==index.str).map(|(_,value)|value)
+	}
+	#[inline]
+	pub fn get_key_value_hashed(&self,index:HashedStr<'_>)->Option<(&K,&V)>{
+		self.entries.find(index.hash,|(key,_)|key.as_str()==index.str).map(|(key,value)|(key,value))
+	}
+	#[inline]
+	pub fn contains_key<'a>(&self,index:impl GetHash+Into<&'a str>)->bool{
+		self.get(index).is_some()
+	}
+	/// Insert a value, returning the previous value.  The existing key is kept.
+	#[inline]
+	pub fn insert(&mut self,key:K,value:V)->Option<V>{
+		let index=HashedStr{hash:key.precomputed_hash(),str:key.as_str()};
+		match self.entries.entry(index.hash,|(k,_)|k.as_str()==index.str,get_key_hash){
+			hash_table::Entry::Occupied(mut entry)=>Some(core::mem::replace(&mut entry.get_mut().1,value)),
+			hash_table::Entry::Vacant(entry)=>{
+				entry.insert((key,value));
+				None
+			},
+		}
+	}
+	#[inline]
+	pub fn remove_hashed(&mut self,index:HashedStr<'_>)->Option<(K,V)>{
+		match self.entries.find_entry(index.hash,|(key,_)|key.as_str()==index.str){
+			Ok(entry)=>Some(entry.remove().0),
+			Err(_)=>None,
+		}
+	}
+	/// Get the entry for a string, hashing it at most once.
+	#[inline]
+	pub fn entry<'a>(&mut self,index:impl GetHash+Into<&'a str>)->HashedStrEntry<'_,'a,K,V>{
+		self.entry_hashed(HashedStr{hash:index.get_hash(),str:index.into()})
+	}
+	/// Get the entry using the precomputed hash.
+	#[inline]
+	pub fn entry_hashed<'a>(&mut self,index:HashedStr<'a>)->HashedStrEntry<'_,'a,K,V>{
+		match self.entries.entry(index.hash,|(key,_)|key.as_str()==index.str,get_key_hash){
+			hash_table::Entry::Occupied(entry)=>HashedStrEntry::Occupied(HashedStrOccupiedEntry{entry}),
+			hash_table::Entry::Vacant(entry)=>HashedStrEntry::Vacant(HashedStrVacantEntry{entry,key:index}),
+		}
+	}
+	#[inline]
+	pub fn reserve(&mut self,additional:usize){
+		self.entries.reserve(additional,get_key_hash)
+	}
+	#[inline]
+	pub fn retain(&mut self,mut f:impl FnMut(&K,&mut V)->bool){
+		self.entries.retain(|(key,value)|f(key,value))
+	}
+}
+
+impl<K:for<'a> From<HashedStr<'a>>+Deref<Target=HashStr>,V> HashedStrMap<K,V>{
+	/// Insert a value by str, hashing it once.  A key is only created if it is not present.
+	#[inline]
+	pub fn insert_str(&mut self,key:&str,value:V)->Option<V>{
+		match self.entry(key){
+			HashedStrEntry::Occupied(mut entry)=>Some(entry.insert(value)),
+			HashedStrEntry::Vacant(entry)=>{
+				entry.insert(value);
+				None
+			},
+		}
+	}
+}
+
+impl<K,V> Default for HashedStrMap<K,V>{
+	#[inline]
+	fn default()->Self{
+		Self::new()
+	}
+}
+
+impl<K:Deref<Target=HashStr>,V> Extend<(K,V)> for HashedStrMap<K,V>{
+	fn extend<I:IntoIterator<Item=(K,V)>>(&mut self,iter:I){
+		for (key,value) in iter{
+			self.insert(key,value);
+		}
+	}
+}
+
+impl<K:Deref<Target=HashStr>,V> FromIterator<(K,V)> for HashedStrMap<K,V>{
+	fn from_iter<I:IntoIterator<Item=(K,V)>>(iter:I)->Self{
+		let mut map=Self::new();
+		map.extend(iter);
+		map
+	}
+}
+
+/// A view into a single entry of a [`HashedStrMap`].
+pub enum HashedStrEntry<'m,'a,K,V>{
+	Occupied(HashedStrOccupiedEntry<'m,K,V>),
+	Vacant(HashedStrVacantEntry<'m,'a,K,V>),
+}
+
+impl<'m,'a,K:Deref<Target=HashStr>,V> HashedStrEntry<'m,'a,K,V>{
+	/// Insert the value with a key created by `make_key` if the entry is vacant.
+	#[inline]
+	pub fn or_insert_keyed(self,make_key:impl FnOnce(HashedStr<'a>)->K,value:V)->&'m mut V{
+		match self{
+			HashedStrEntry::Occupied(entry)=>entry.into_mut(),
+			HashedStrEntry::Vacant(entry)=>{
+				let key=make_key(entry.key);
+				entry.insert_key(key,value)
+			},
+		}
+	}
+	#[inline]
+	pub fn and_modify(mut self,f:impl FnOnce(&mut V))->Self{
+		if let HashedStrEntry::Occupied(entry)=&mut self{
+			f(entry.get_mut());
+		}
+		self
+	}
+}
+
+impl<'m,'a,K:From<HashedStr<'a>>+Deref<Target=HashStr>,V> HashedStrEntry<'m,'a,K,V>{
+	#[inline]
+	pub fn or_insert(self,value:V)->&'m mut V{
+		self.or_insert_with(||value)
+	}
+	#[inline]
+	pub fn or_insert_with(self,f:impl FnOnce()->V)->&'m mut V{
+		match self{
+			HashedStrEntry::Occupied(entry)=>entry.into_mut(),
+			HashedStrEntry::Vacant(entry)=>entry.insert(f()),
+		}
+	}
+	/// Insert a value computed from the key if the entry is vacant.
+	#[inline]
+	pub fn or_insert_with_key(self,f:impl FnOnce(HashedStr<'a>)->V)->&'m mut V{
+		match self{
+			HashedStrEntry::Occupied(entry)=>entry.into_mut(),
+			HashedStrEntry::Vacant(entry)=>{
+				let value=f(entry.key);
+				entry.insert(value)
+			},
+		}
+	}
+	#[inline]
+	pub fn or_default(self)->&'m mut V where V:Default{
+		self.or_insert_with(V::default)
+	}
+}
+
+impl<'m,'host,V> HashedStrEntry<'m,'_,&'host HashStr,V>{
+	/// Insert the value if the entry is vacant, allocating the key
+	/// on the provided HashStrHost with the precomputed hash.
+	#[inline]
+	pub fn or_insert_in(self,host:&'host HashStrHost,value:V)->&'m mut V{
+		self.or_insert_keyed(|key|host.alloc_str_with_hash(key.hash,key.str),value)
+	}
+}
+
+/// An occupied entry of a [`HashedStrMap`].
+pub struct HashedStrOccupiedEntry<'m,K,V>{
+	entry:hash_table::OccupiedEntry<'m,(K,V)>,
+}
+
+impl<'m,K,V> HashedStrOccupiedEntry<'m,K,V>{
+	#[inline]
+	pub fn key(&self)->&K{
+		&self.entry.get().0
+	}
+	#[inline]
+	pub fn get(&self)->&V{
+		&self.entry.get().1
+	}
+	#[inline]
+	pub fn get_mut(&mut self)->&mut V{
+		&mut self.entry.get_mut().1
+	}
+	#[inline]
+	pub fn into_mut(self)->&'m mut V{
+		&mut self.entry.into_mut().1
+	}
+	/// Replace the value, returning the previous value.
+	#[inline]
+	pub fn insert(&mut self,value:V)->V{
+		core::mem::replace(self.get_mut(),value)
+	}
+	#[inline]
+	pub fn remove(self)->(K,V){
+		self.entry.remove().0
+	}
+}
+
+/// A vacant entry of a [`HashedStrMap`], holding the hashed index str.
+pub struct HashedStrVacantEntry<'m,'a,K,V>{
+	entry:hash_table::VacantEntry<'m,(K,V)>,
+	key:HashedStr<'a>,
+}
+
+impl<'m,'a,K:Deref<Target=HashStr>,V> HashedStrVacantEntry<'m,'a,K,V>{
+	#[inline]
+	pub fn key(&self)->HashedStr<'a>{
+		self.key
+	}
+	/// Insert the value with the provided key, which must match the index str.
+	#[inline]
+	pub fn insert_key(self,key:K,value:V)->&'m mut V{
+		assert!(key.precomputed_hash()==self.key.hash&&key.as_str()==self.key.str,"key does not match the entry");
+		&mut self.entry.insert((key,value)).into_mut().1
+	}
+}
+
+impl<'m,'a,K:From<HashedStr<'a>>+Deref<Target=HashStr>,V> HashedStrVacantEntry<'m,'a,K,V>{
+	/// Insert the value, creating the key with the precomputed hash.
+	#[inline]
+	pub fn insert(self,value:V)->&'m mut V{
+		&mut self.entry.insert((self.key.into(),value)).into_mut().1
+	}
+}
+
+#[test]
+fn test_hashed_str_map(){
+	use alloc::boxed::Box;
+	let mut map=HashedStrMap::<Box<HashStr>,u32>::new();
+	assert_eq!(map.insert_str("a",1),None);
+	assert_eq!(map.insert_str("a",2),Some(1));
+	map.insert(HashStr::anonymous("b".into()),3);
+	assert_eq!(map.len(),2);
+
+	let a=HashedStr::new("a");
+	assert_eq!(map.get_hashed(a),Some(&2));
+	*map.get_hashed_mut(a).unwrap()+=1;
+	assert_eq!(map.get("a"),Some(&3));
+	assert_eq!(map.get(&*HashStr::anonymous("b".into())),Some(&3));
+	assert!(!map.contains_key("c"));
+
+	map.entry_hashed(HashedStr::new("c")).and_modify(|v|*v=0).or_insert(7);
+	map.entry("c").and_modify(|v|*v+=1).or_default();
+	assert_eq!(map.get("c"),Some(&8));
+	assert_eq!(*map.entry("dd").or_insert_with_key(|key|key.str.len() as u32),2);
+	assert_eq!(map.remove_hashed(HashedStr::new("dd")).map(|(_,v)|v),Some(2));
+
+	let (key,value)=map.remove_hashed(a).unwrap();
+	assert_eq!((key.as_str(),value),("a",3));
+	map.retain(|_,v|*v<8);
+	assert_eq!(map.keys().map(|k|k.as_str()).collect::<alloc::vec::Vec<_>>(),["b"]);
+}
+
+#[test]
+fn test_hashed_str_map_borrowed(){
+	let host=HashStrHost::new();
+	let mut map=HashedStrMap::<&HashStr,u32>::default();
+	*map.entry("a").or_insert_in(&host,0)+=1;
+	*map.entry("a").or_insert_in(&host,0)+=1;
+	assert_eq!(map.get("a"),Some(&2));
+	let key=*map.keys().next().unwrap();
+	assert_eq!(key.precomputed_hash(),HashedStr::new("a").precomputed_hash());
+}
+
+#[test]
+fn test_hashbrown_map(){
+	use alloc::boxed::Box;
+	let words=["a","b"].map(|s|HashStr::anonymous(s.into()));
+	let mut map=HashbrownHashStrMap::default();
+	map.insert(&*words[0],0);
+	map.insert(&*words[1],1);
+	assert_eq!(map.get(&HashedStr::new("b")),Some(&1));
+	assert_eq!(map.get(UnhashedStr::from_ref("a")),Some(&0));
+	// a str built at runtime is hashed on the fly
+	let runtime=alloc::string::String::from("b");
+	assert_eq!(map.get_str(&runtime),Some(&1));
+	assert_eq!(map.get_key_value_str(&runtime).map(|(k,_)|k.precomputed_hash()),Some(words[1].precomputed_hash()));
+	assert_eq!(map.get_str("c"),None);
+	*map.get_str_mut("b").unwrap()+=1;
+	assert_eq!(map.get(&HashedStr::new("b")),Some(&2));
+	assert_eq!(map.remove_str("a"),Some(0));
+	assert!(!map.contains_str("a"));
+	assert!(!map.contains_key(&HashedStr::new("a")));
+	drop(map);
+
+	// owned keys work too
+	let set:hashbrown::HashSet<Box<HashStr>,BuildHasherDefault<IdentityHasher>>=words.into_iter().collect();
+	assert!(set.contains(&HashedStr::new("a")));
+	assert!(set.contains(UnhashedStr::from_ref("b")));
+	let c=HashStr::anonymous("c".into());
+	let set:HashbrownHashStrSet=[&*c].into_iter().collect();
+	assert!(set.contains(&HashedStr::new("c")));
+	assert!(set.contains(UnhashedStr::from_ref("c")));
+	assert_eq!(set.get(UnhashedStr::from_ref("c")).map(|k|k.as_str()),Some("c"));
+	let mut set=set;
+	assert_eq!(set.get_str("c").map(HashStr::as_str),Some("c"));
+	assert!(set.contains_str("c"));
+	assert!(set.remove_str("c"));
+	assert!(!set.contains_str("c"));
+}
@@ -6,7 +6,10 @@
 use crate::hash::make_hash;
 use crate::hash_str::HashStr;
 
-use std::borrow::Cow;
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::sync::Arc;
 
 impl<'a> From<&'a HashStr> for &'a str{
 	#[inline]
@@ -46,31 +49,31 @@ impl Eq for HashStr{}
 impl PartialOrd for HashStr{
 	#[inline]
 	fn partial_cmp(&self,other:&Self)->Option<core::cmp::Ordering>{
-		self.as_str().partial_cmp(other.as_str())
+		Some(self.cmp(other))
 	}
 }
 impl Ord for HashStr{
 	#[inline]
-	fn cmp(&self,other:&Self)->std::cmp::Ordering{
+	fn cmp(&self,other:&Self)->core::cmp::Ordering{
 		self.as_str().cmp(other.as_str())
 	}
 }
 
 /// Helper type for indexing a HashMap without allocation
 /// Unhashed str is hashed on the fly instead of using a precalculated hash.
-/// Useful for indexing a HashMap without needing to allocate a Box<HashStr>
+/// Useful for indexing a HashMap without needing to allocate a `Box<HashStr>`
 #[repr(transparent)]
 #[derive(Debug,PartialEq,Eq,PartialOrd,Ord)]
 pub struct UnhashedStr(str);
 impl UnhashedStr{
 	#[inline]
-	pub const fn from_ref<'a>(str:&'a str)->&'a Self{
+	pub const fn from_ref(str:&str)->&Self{
 		// SAFETY: UnhashedStr is #[repr(transparent)]
 		let ptr=str as *const str as *const Self;
 		unsafe{&*ptr}
 	}
 	#[inline]
-	pub const fn as_str<'a>(&'a self)->&'a str{
+	pub const fn as_str(&self)->&str{
 		// SAFETY: UnhashedStr is #[repr(transparent)]
 		let ptr=self as *const Self as *const str;
 		unsafe{&*ptr}
@@ -78,7 +81,7 @@ impl UnhashedStr{
 }
 impl core::hash::Hash for UnhashedStr{
 	#[inline]
-	fn hash<H:std::hash::Hasher>(&self,state:&mut H){
+	fn hash<H:core::hash::Hasher>(&self,state:&mut H){
 		let hash=make_hash(self.into());
 		state.write_u64(hash);
 	}
@@ -96,10 +99,60 @@ impl<'a> From<&'a UnhashedStr> for &'a str{
 	}
 }
 
-impl core::borrow::Borrow<UnhashedStr> for &HashStr{
+macro_rules! impl_borrow_unhashed{
+	($ty:ty)=>{
+		impl core::borrow::Borrow<UnhashedStr> for $ty{
+			#[inline]
+			fn borrow(&self)->&UnhashedStr{
+				UnhashedStr::from_ref(self.as_str())
+			}
+		}
+	};
+}
+impl_borrow_unhashed!(&HashStr);
+impl_borrow_unhashed!(Box<HashStr>);
+impl_borrow_unhashed!(Arc<HashStr>);
+
+impl From<&HashStr> for Box<HashStr>{
 	#[inline]
-	fn borrow(&self)->&UnhashedStr{
-		UnhashedStr::from_ref(self.as_str())
+	fn from(value:&HashStr)->Self{
+		HashStr::boxed_str_with_hash(value.precomputed_hash(),value.as_str())
+	}
+}
+impl From<&HashStr> for Arc<HashStr>{
+	#[inline]
+	fn from(value:&HashStr)->Self{
+		Box::<HashStr>::from(value).into()
+	}
+}
+impl From<HashedStr<'_>> for Box<HashStr>{
+	#[inline]
+	fn from(value:HashedStr<'_>)->Self{
+		HashStr::boxed_str_with_hash(value.hash,value.str)
+	}
+}
+impl From<HashedStr<'_>> for Arc<HashStr>{
+	#[inline]
+	fn from(value:HashedStr<'_>)->Self{
+		Box::<HashStr>::from(value).into()
+	}
+}
+impl<'a> From<&'a Box<HashStr>> for &'a HashStr{
+	#[inline]
+	fn from(value:&'a Box<HashStr>)->Self{
+		value
+	}
+}
+impl<'a> From<&'a Arc<HashStr>> for &'a HashStr{
+	#[inline]
+	fn from(value:&'a Arc<HashStr>)->Self{
+		value
+	}
+}
+impl Clone for Box<HashStr>{
+	#[inline]
+	fn clone(&self)->Self{
+		(**self).into()
 	}
 }
 
@@ -111,7 +164,8 @@ impl core::borrow::Borrow<UnhashedStr> for &HashStr{
 /// Note:
 /// This would be convenient for indexing a HashStrMap but unfortunately the
 /// type signature of `hash_map.get()` precludes this as a possiblity.
-/// Use UnhashedStr instead for quick and dirty one-time indexing.
+/// Use HashedStrMap to index with a HashedStr, or
+/// UnhashedStr instead for quick and dirty one-time indexing.
 #[derive(Debug,Clone,Copy)]
 pub struct HashedStr<'a>{
 	pub(crate) hash:u64,
@@ -119,7 +173,7 @@ pub struct HashedStr<'a>{
 }
 impl<'a> HashedStr<'a>{
 	#[inline]
-	pub fn new(str:&'a str)->Self{
+	pub const fn new(str:&'a str)->Self{
 		let hash=make_hash(str);
 		HashedStr{
 			hash,
@@ -131,6 +185,21 @@ impl<'a> HashedStr<'a>{
 		self.hash
 	}
 }
+impl core::hash::Hash for HashedStr<'_>{
+	#[inline]
+	fn hash<H:core::hash::Hasher>(&self,state:&mut H){
+		state.write_u64(self.hash);
+	}
+}
+/// Index hashbrown and indexmap maps keyed by any HashStr pointer with a HashedStr.
+/// HashedStr must not implement Eq, which would conflict with the blanket impl.
+#[cfg(any(feature="cache",feature="indexmap"))]
+impl<K:core::ops::Deref<Target=HashStr>> equivalent::Equivalent<K> for HashedStr<'_>{
+	#[inline]
+	fn equivalent(&self,key:&K)->bool{
+		self.hash==key.precomputed_hash()&&self.str==key.as_str()
+	}
+}
 impl<'a> From<HashedStr<'a>> for &'a str{
 	#[inline]
 	fn from(value:HashedStr<'a>)->Self{
@@ -187,6 +256,18 @@ impl GetHash for &HashStr{
 		self.precomputed_hash()
 	}
 }
+impl GetHash for Box<HashStr>{
+	#[inline]
+	fn get_hash(&self)->u64{
+		self.precomputed_hash()
+	}
+}
+impl GetHash for Arc<HashStr>{
+	#[inline]
+	fn get_hash(&self)->u64{
+		self.precomputed_hash()
+	}
+}
 impl GetHash for HashedStr<'_>{
 	#[inline]
 	fn get_hash(&self)->u64{
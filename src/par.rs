@@ -0,0 +1,160 @@
+use rayon::prelude::*;
+use crate::ornaments::GetHash;
+use crate::hash_str::HashStr;
+use std::sync::{Mutex,PoisonError};
+use crate::cache::{HashStrHost,HashStrCache,Presence};
+
+/// A pool of HashStrHosts for [`HashStrCache::par_intern_with`].  Each thread
+/// locks one host while it interns its chunk, so the pool may be shared by any
+/// number of caches and batches.  HashStrs allocated in the pool live as long as it.
+pub struct HashStrHostPool(Vec<Mutex<HashStrHost>>);
+
+impl HashStrHostPool{
+	/// A pool with one host per rayon thread.
+	#[inline]
+	pub fn new()->Self{
+		Self::with_hosts(rayon::current_num_threads())
+	}
+	#[inline]
+	pub fn with_hosts(count:usize)->Self{
+		assert!(count!=0,"at least one host is required");
+		Self((0..count).map(|_|Mutex::new(HashStrHost::new())).collect())
+	}
+	#[inline]
+	pub fn len(&self)->usize{
+		self.0.len()
+	}
+	#[inline]
+	pub fn is_empty(&self)->bool{
+		self.0.is_empty()
+	}
+}
+
+impl Default for HashStrHostPool{
+	#[inline]
+	fn default()->Self{
+		Self::new()
+	}
+}
+
+impl<'host> HashStrCache<'host>{
+	/// Intern strings in parallel, returning the interned HashStrs in input order.
+	/// The items are split into one chunk per host of the pool, and each chunk is
+	/// interned on its own thread into its own host.  The results are then merged
+	/// into this cache so that equal strings always point to the same HashStr.
+	///
+	/// A string which is new to this cache and appears in multiple chunks
+	/// is allocated once per chunk, but only the first allocation is used.
+	///
+	/// ```rust
+	/// use hash_str::{HashStrHostPool,HashStrCache};
+	///
+	/// let pool=HashStrHostPool::new();
+	/// let mut cache=HashStrCache::new();
+	///
+	/// let words=cache.par_intern_with(&pool,&["a","b","a"]);
+	/// assert!(core::ptr::addr_eq(words[0],words[2]));
+	/// let more=cache.par_intern_with(&pool,&["b","c"]);
+	/// assert!(core::ptr::addr_eq(words[1],more[0]));
+	/// ```
+	pub fn par_intern_with<T>(&mut self,pool:&'host HashStrHostPool,items:&[T])->Vec<&'host HashStr>
+		where
+			T:GetHash+AsRef<str>+Sync,
+	{
+		let chunk_size=items.len().div_ceil(pool.len()).max(1);
+		let this=&*self;
+		let interned:Vec<Vec<&'host HashStr>>=items.par_chunks(chunk_size).zip(pool.0.par_iter()).map(|(chunk,host)|{
+			let host=host.lock().unwrap_or_else(PoisonError::into_inner);
+			let mut local=HashStrCache::new();
+			chunk.iter().map(|index|{
+				let (hash,str)=(index.get_hash(),index.as_ref());
+				match this.presence_str_with_hash(hash,str){
+					Presence::Present(hash_str)=>hash_str,
+					Presence::Absent(_)=>local.intern_str_with_hash(||{
+						// SAFETY: the host is locked, so no other thread allocates in it.
+						// Allocations never move and the host is only dropped with
+						// the pool, which is borrowed for 'host
+						let ptr=host.alloc_str_with_hash(hash,str) as *const HashStr;
+						unsafe{&*ptr}
+					},hash,str),
+				}
+			}).collect()
+		}).collect();
+		// deduplicate strings which were interned into multiple hosts
+		interned.into_iter().flatten().map(|hash_str|self.cache(hash_str)).collect()
+	}
+}
+
+#[cfg(feature="global")]
+impl<'host> crate::global::Bins<'host>{
+	/// Intern strings into the global cache in parallel, returning the interned
+	/// HashStrs in input order.  Hashes are calculated in parallel and the items
+	/// are grouped by bin so that each bin is locked only once.
+	pub fn par_intern<T>(&self,items:&[T])->Vec<&'host HashStr>
+		where
+			T:GetHash+AsRef<str>+Sync,
+	{
+		use crate::global::whichbin;
+		let hashes:Vec<(usize,u64)>=items.par_iter().map(|index|{
+			let hash=index.get_hash();
+			(whichbin(hash),hash)
+		}).collect();
+		// item indices sorted by bin
+		let mut order:Vec<usize>=(0..items.len()).collect();
+		order.par_sort_unstable_by_key(|&i|hashes[i].0);
+		let groups:Vec<&[usize]>=order.chunk_by(|&a,&b|hashes[a].0==hashes[b].0).collect();
+		let interned:Vec<Vec<&'host HashStr>>=groups.par_iter().map(|group|{
+			let mut bin=self.lock_bin(hashes[group[0]].0);
+			bin.cache.reserve(group.len());
+			group.iter().map(|&i|bin.intern_str_with_hash(hashes[i].1,items[i].as_ref())).collect()
+		}).collect();
+		let mut ordered=vec![None;items.len()];
+		for (group,interned) in groups.into_iter().zip(interned){
+			for (&i,hash_str) in group.iter().zip(interned){
+				ordered[i]=Some(hash_str);
+			}
+		}
+		ordered.into_iter().map(|hash_str|hash_str.unwrap()).collect()
+	}
+}
+
+#[test]
+fn test_par_intern_with(){
+	let pool=HashStrHostPool::with_hosts(4);
+	let host=HashStrHost::new();
+	let mut cache=HashStrCache::new();
+	let existing=cache.intern_with(&host,"b");
+	let words=["a","b","c","a","d","c","e","a"];
+	let interned=cache.par_intern_with(&pool,&words);
+	assert_eq!(interned,words);
+	assert!(core::ptr::addr_eq(interned[1],existing));
+	assert!(core::ptr::addr_eq(interned[0],interned[3]));
+	assert!(core::ptr::addr_eq(interned[0],interned[7]));
+	assert!(core::ptr::addr_eq(interned[2],interned[5]));
+	assert_eq!(cache.len(),5);
+
+	// a second batch on the same cache and pool reuses the first
+	let more=["e","f","a","g","f"];
+	let interned_more=cache.par_intern_with(&pool,&more);
+	assert_eq!(interned_more,more);
+	assert!(core::ptr::addr_eq(interned_more[0],interned[6]));
+	assert!(core::ptr::addr_eq(interned_more[2],interned[0]));
+	assert!(core::ptr::addr_eq(interned_more[1],interned_more[4]));
+	assert_eq!(cache.len(),7);
+
+	// the pool can be shared by another cache at the same time
+	let mut other=HashStrCache::new();
+	let interned_other=other.par_intern_with(&pool,&words);
+	assert_eq!(interned_other,words);
+	assert_eq!(interned.len()+interned_other.len(),16);
+}
+
+#[cfg(feature="global")]
+#[test]
+fn test_par_intern(){
+	let words:Vec<String>=(0..1000).map(|i|format!("par_intern_{}",i%300)).collect();
+	let interned=crate::global::get_cache().par_intern(&words);
+	assert_eq!(interned,words);
+	assert!(core::ptr::addr_eq(interned[0],interned[300]));
+	assert!(core::ptr::addr_eq(interned[299],interned[899]));
+}
@@ -0,0 +1,481 @@
+use crate::ornaments::GetHash;
+use crate::hash_str::HashStr;
+
+// Compile time perfect hashing using hash and displace.
+// Keys are split into buckets, and each bucket is assigned a pair of
+// displacements which place all of its keys into unoccupied slots.
+// Lookup is one displacement read and one slot read, no collision chain.
+// There are more slots than keys so the last buckets find free slots quickly.
+
+// Average number of keys per bucket
+const KEYS_PER_BUCKET:usize=5;
+// Give up on a seed after this many displacements for a single bucket
+const MAX_DISPLACEMENTS:usize=1<<12;
+// d2 only shifts a bucket, so d1 which scatters it changes every this many attempts
+const DISPLACEMENT_STRIDE:usize=64;
+const MAX_SEED_ATTEMPTS:u64=64;
+const EMPTY:u32=u32::MAX;
+
+#[doc(hidden)]
+pub const fn phf_bucket_count(len:usize)->usize{
+	if len==0{
+		1
+	}else{
+		len.div_ceil(KEYS_PER_BUCKET)
+	}
+}
+
+/// The number of slots for `len` keys, for a load factor of 0.8.
+#[doc(hidden)]
+pub const fn phf_slot_count(len:usize)->usize{
+	len+len.div_ceil(4)
+}
+
+/// Panics if any two hashes are equal.  Used to detect
+/// duplicate keys and hash collisions at compile time.
+#[doc(hidden)]
+pub const fn assert_unique_hashes(hashes:&[u64]){
+	let mut i=0;
+	while i<hashes.len(){
+		let mut j=0;
+		while j<i{
+			if hashes[i]==hashes[j]{
+				panic!("duplicate key or hash collision");
+			}
+			j+=1;
+		}
+		i+=1;
+	}
+}
+
+/// Panics if any two HashStrs have the same hash.
+#[doc(hidden)]
+pub const fn assert_unique_hash_strs(keys:&[&HashStr]){
+	let mut i=0;
+	while i<keys.len(){
+		let mut j=0;
+		while j<i{
+			if keys[i].precomputed_hash()==keys[j].precomputed_hash(){
+				panic!("duplicate key or hash collision");
+			}
+			j+=1;
+		}
+		i+=1;
+	}
+}
+
+const fn cmp_str(a:&str,b:&str)->core::cmp::Ordering{
+	use core::cmp::Ordering;
+	let (a,b)=(a.as_bytes(),b.as_bytes());
+	let mut i=0;
+	while i<a.len()&&i<b.len(){
+		if a[i]!=b[i]{
+			return if a[i]<b[i]{Ordering::Less}else{Ordering::Greater};
+		}
+		i+=1;
+	}
+	if a.len()<b.len(){
+		Ordering::Less
+	}else if b.len()<a.len(){
+		Ordering::Greater
+	}else{
+		Ordering::Equal
+	}
+}
+
+/// Sort HashStrs by string at compile time.  Used by `hstr_sorted_array!`.
+/// Panics if there are duplicate keys.
+#[doc(hidden)]
+pub const fn sort_hash_strs<const N:usize>(keys:&[&'static HashStr])->[&'static HashStr;N]{
+	let Some(&keys)=keys.first_chunk::<N>()else{
+		panic!("wrong number of keys");
+	};
+	let mut sorted=keys;
+	let mut i=1;
+	while i<N{
+		let mut j=i;
+		while 0<j{
+			match cmp_str(sorted[j-1].as_str(),sorted[j].as_str()){
+				core::cmp::Ordering::Less=>break,
+				core::cmp::Ordering::Equal=>panic!("duplicate key"),
+				core::cmp::Ordering::Greater=>{
+					let swap=sorted[j-1];
+					sorted[j-1]=sorted[j];
+					sorted[j]=swap;
+				},
+			}
+			j-=1;
+		}
+		i+=1;
+	}
+	sorted
+}
+
+// splitmix64 finalizer
+#[inline]
+const fn mix(mut x:u64)->u64{
+	x=(x^(x>>30)).wrapping_mul(0xbf58476d1ce4e5b9);
+	x=(x^(x>>27)).wrapping_mul(0x94d049bb133111eb);
+	x^(x>>31)
+}
+
+/// Derive the bucket selector and the two displacement factors from a hash.
+#[inline]
+const fn split_hash(hash:u64,seed:u64)->(u32,u32,u32){
+	let a=mix(hash^seed);
+	let b=mix(a);
+	(a as u32,(a>>32) as u32,b as u32)
+}
+
+#[inline]
+const fn displace(f1:u32,f2:u32,d1:u32,d2:u32,len:usize)->usize{
+	((f1 as u64+(d1 as u64)*(f2 as u64)+d2 as u64)%len as u64) as usize
+}
+
+/// Perfect hash index built at compile time by `hstr_map!`.
+#[doc(hidden)]
+pub struct Phf<const N:usize,const B:usize,const S:usize>{
+	pub seed:u64,
+	pub disps:[(u32,u32);B],
+	pub map:[u32;S],
+}
+
+impl<const N:usize,const B:usize,const S:usize> Phf<N,B,S>{
+	/// Panics if there are duplicate keys.
+	pub const fn build(keys:&[&HashStr])->Self{
+		assert!(keys.len()==N);
+		assert!(B==phf_bucket_count(N));
+		assert!(S==phf_slot_count(N));
+		assert!(S<EMPTY as usize,"too many keys");
+		let mut hashes=[0;N];
+		let mut i=0;
+		while i<N{
+			hashes[i]=keys[i].precomputed_hash();
+			i+=1;
+		}
+		let mut seed_attempt=0;
+		while seed_attempt<MAX_SEED_ATTEMPTS{
+			let seed=mix(seed_attempt);
+			if let Some(phf)=Self::try_build(&hashes,seed){
+				return phf;
+			}
+			seed_attempt+=1;
+		}
+		panic!("failed to build perfect hash");
+	}
+	const fn try_build(hashes:&[u64;N],seed:u64)->Option<Self>{
+		let mut split=[(0,0,0);N];
+		let mut bucket_len=[0usize;B];
+		let mut i=0;
+		while i<N{
+			split[i]=split_hash(hashes[i],seed);
+			bucket_len[split[i].0 as usize%B]+=1;
+			i+=1;
+		}
+		// group the keys by bucket
+		let mut bucket_start=[0usize;B];
+		let mut b=1;
+		while b<B{
+			bucket_start[b]=bucket_start[b-1]+bucket_len[b-1];
+			b+=1;
+		}
+		let mut bucket_keys=[0usize;N];
+		let mut bucket_fill=bucket_start;
+		let mut i=0;
+		while i<N{
+			let b=split[i].0 as usize%B;
+			bucket_keys[bucket_fill[b]]=i;
+			bucket_fill[b]+=1;
+			i+=1;
+		}
+		// equal hashes always share a bucket
+		let mut max_len=0;
+		let mut b=0;
+		while b<B{
+			let (start,len)=(bucket_start[b],bucket_len[b]);
+			let mut k=start;
+			while k<start+len{
+				let mut j=start;
+				while j<k{
+					if hashes[bucket_keys[k]]==hashes[bucket_keys[j]]{
+						panic!("duplicate key or hash collision");
+					}
+					j+=1;
+				}
+				k+=1;
+			}
+			if max_len<len{
+				max_len=len;
+			}
+			b+=1;
+		}
+		let mut disps=[(0,0);B];
+		let mut map=[EMPTY;S];
+		// slots tentatively taken by the current displacement attempt
+		let mut try_map=[0usize;S];
+		let mut generation=0;
+		// place the largest buckets first while most slots are free
+		let mut len=max_len;
+		while 0<len{
+			let mut bucket=0;
+			while bucket<B{
+				if bucket_len[bucket]==len{
+					let start=bucket_start[bucket];
+					let mut placed=false;
+					let mut attempt=0;
+					while attempt<MAX_DISPLACEMENTS{
+						let (d1,d2)=((attempt/DISPLACEMENT_STRIDE) as u32,(attempt%DISPLACEMENT_STRIDE) as u32);
+						generation+=1;
+						let mut fits=true;
+						let mut k=start;
+						while k<start+len{
+							let (_,f1,f2)=split[bucket_keys[k]];
+							let slot=displace(f1,f2,d1,d2,S);
+							if map[slot]!=EMPTY||try_map[slot]==generation{
+								fits=false;
+								break;
+							}
+							try_map[slot]=generation;
+							k+=1;
+						}
+						if fits{
+							let mut k=start;
+							while k<start+len{
+								let (_,f1,f2)=split[bucket_keys[k]];
+								map[displace(f1,f2,d1,d2,S)]=bucket_keys[k] as u32;
+								k+=1;
+							}
+							disps[bucket]=(d1,d2);
+							placed=true;
+							break;
+						}
+						attempt+=1;
+					}
+					if !placed{
+						return None;
+					}
+				}
+				bucket+=1;
+			}
+			len-=1;
+		}
+		Some(Phf{seed,disps,map})
+	}
+}
+
+/// Borrowed perfect hash index used for lookups at runtime.
+#[doc(hidden)]
+#[derive(Debug,Clone,Copy)]
+pub struct PhfIndex{
+	pub seed:u64,
+	pub disps:&'static [(u32,u32)],
+	pub map:&'static [u32],
+}
+
+impl PhfIndex{
+	/// The index of the only key which may have this hash.
+	#[inline]
+	pub const fn get_index(&self,hash:u64)->Option<usize>{
+		if self.map.is_empty(){
+			return None;
+		}
+		let (g,f1,f2)=split_hash(hash,self.seed);
+		let (d1,d2)=self.disps[g as usize%self.disps.len()];
+		match self.map[displace(f1,f2,d1,d2,self.map.len())]{
+			EMPTY=>None,
+			i=>Some(i as usize),
+		}
+	}
+	/// The index of the key equal to the string.
+	#[inline]
+	pub fn find(&self,keys:&[&HashStr],hash:u64,str:&str)->Option<usize>{
+		let i=self.get_index(hash)?;
+		let key=keys[i];
+		(key.precomputed_hash()==hash&&key.as_str()==str).then_some(i)
+	}
+}
+
+/// A map with `&'static HashStr` keys built at compile time using a
+/// perfect hash function.  Create one with the `hstr_map!` macro.
+/// Lookups use the precomputed hash to find the only possible entry with
+/// a single probe, then confirm it with a string compare.
+#[derive(Debug)]
+pub struct StaticHashStrMap<V:'static>{
+	#[doc(hidden)]
+	pub index:PhfIndex,
+	#[doc(hidden)]
+	pub keys:&'static [&'static HashStr],
+	#[doc(hidden)]
+	pub values:&'static [V],
+}
+
+impl<V> StaticHashStrMap<V>{
+	#[inline]
+	fn find(&self,hash:u64,str:&str)->Option<usize>{
+		self.index.find(self.keys,hash,str)
+	}
+	/// Get the value for a key, utilizing the precalculated hash if possible.
+	#[inline]
+	pub fn get(&self,index:impl GetHash+AsRef<str>)->Option<&'static V>{
+		self.get_key_value(index).map(|(_,value)|value)
+	}
+	#[inline]
+	pub fn get_key_value(&self,index:impl GetHash+AsRef<str>)->Option<(&'static HashStr,&'static V)>{
+		let i=self.find(index.get_hash(),index.as_ref())?;
+		Some((self.keys[i],&self.values[i]))
+	}
+	#[inline]
+	pub fn contains_key(&self,index:impl GetHash+AsRef<str>)->bool{
+		self.find(index.get_hash(),index.as_ref()).is_some()
+	}
+	#[inline]
+	pub const fn len(&self)->usize{
+		self.keys.len()
+	}
+	#[inline]
+	pub const fn is_empty(&self)->bool{
+		self.keys.is_empty()
+	}
+	/// Keys in declaration order.
+	#[inline]
+	pub fn keys(&self)->impl Iterator<Item=&'static HashStr>{
+		self.keys.iter().copied()
+	}
+	/// Values in declaration order.
+	#[inline]
+	pub fn values(&self)->impl Iterator<Item=&'static V>{
+		self.values.iter()
+	}
+	/// Entries in declaration order.
+	#[inline]
+	pub fn iter(&self)->impl Iterator<Item=(&'static HashStr,&'static V)>{
+		self.keys.iter().copied().zip(self.values.iter())
+	}
+}
+
+/// A set of `&'static HashStr` built at compile time using a perfect
+/// hash function.  Create one with the `include_hstr_set!` macro.
+#[derive(Debug)]
+pub struct StaticHashStrSet{
+	#[doc(hidden)]
+	pub index:PhfIndex,
+	#[doc(hidden)]
+	pub keys:&'static [&'static HashStr],
+}
+
+impl StaticHashStrSet{
+	/// Get the HashStr equal to the string, utilizing the precalculated hash if possible.
+	#[inline]
+	pub fn get(&self,index:impl GetHash+AsRef<str>)->Option<&'static HashStr>{
+		let i=self.index.find(self.keys,index.get_hash(),index.as_ref())?;
+		Some(self.keys[i])
+	}
+	#[inline]
+	pub fn contains(&self,index:impl GetHash+AsRef<str>)->bool{
+		self.index.find(self.keys,index.get_hash(),index.as_ref()).is_some()
+	}
+	#[inline]
+	pub const fn len(&self)->usize{
+		self.keys.len()
+	}
+	#[inline]
+	pub const fn is_empty(&self)->bool{
+		self.keys.is_empty()
+	}
+	/// Keys in declaration order.
+	#[inline]
+	pub fn iter(&self)->impl Iterator<Item=&'static HashStr>{
+		self.keys.iter().copied()
+	}
+}
+
+/// Construct a `StaticHashStrMap` at compile time.  Keys are hashed with the
+/// same hash as `hstr!`, and duplicate keys are a compile error.
+///
+/// ```rust
+/// use hash_str::{hstr,hstr_map,HashedStr,StaticHashStrMap};
+///
+/// #[derive(Debug,PartialEq)]
+/// enum Tok{If,Else,While}
+///
+/// static KEYWORDS:StaticHashStrMap<Tok>=hstr_map!{
+///     "if"=>Tok::If,
+///     "else"=>Tok::Else,
+///     "while"=>Tok::While,
+/// };
+///
+/// assert_eq!(KEYWORDS.get(hstr!("if")),Some(&Tok::If));
+/// assert_eq!(KEYWORDS.get(HashedStr::new("while")),Some(&Tok::While));
+/// assert_eq!(KEYWORDS.get("loop"),None);
+/// ```
+///
+/// ```rust,compile_fail
+/// let map:hash_str::StaticHashStrMap<u32>=hash_str::hstr_map!{"a"=>1,"a"=>2};
+/// ```
+#[macro_export]
+macro_rules! hstr_map{
+	($($key:literal=>$value:expr),* $(,)?)=>{
+		{
+			const KEYS:&[&$crate::HashStr]=&[$($crate::hstr!($key)),*];
+			const N:usize=KEYS.len();
+			const B:usize=$crate::phf_bucket_count(N);
+			const S:usize=$crate::phf_slot_count(N);
+			const PHF:$crate::Phf<N,B,S>=$crate::Phf::build(KEYS);
+			$crate::StaticHashStrMap{
+				index:$crate::PhfIndex{
+					seed:PHF.seed,
+					disps:&PHF.disps,
+					map:&PHF.map,
+				},
+				keys:KEYS,
+				values:&[$($value),*],
+			}
+		}
+	};
+}
+
+#[test]
+fn test_hstr_map(){
+	use crate::hstr;
+	use crate::ornaments::HashedStr;
+	static MAP:StaticHashStrMap<u32>=crate::hstr_map!{
+		"zero"=>0,
+		"one"=>1,
+		"two"=>2,
+		"three"=>3,
+		"four"=>4,
+		"five"=>5,
+		"six"=>6,
+		"seven"=>7,
+		"eight"=>8,
+		"nine"=>9,
+		"ten"=>10,
+		"eleven"=>11,
+		"twelve"=>12,
+	};
+	assert_eq!(MAP.len(),13);
+	for (i,(key,&value)) in MAP.iter().enumerate(){
+		assert_eq!(value,i as u32);
+		assert_eq!(MAP.get(key),Some(&value));
+		assert_eq!(MAP.get(HashedStr::new(key)),Some(&value));
+		assert_eq!(MAP.get(key.as_str()),Some(&value));
+	}
+	assert_eq!(MAP.get(hstr!("twelve")),Some(&12));
+	assert_eq!(MAP.get("thirteen"),None);
+
+	let empty:StaticHashStrMap<u32>=crate::hstr_map!{};
+	assert!(empty.is_empty());
+	assert_eq!(empty.get("zero"),None);
+}
+
+#[test]
+fn test_hstr_map_large(){
+	static MAP:StaticHashStrMap<u32>=include!("../tests/large_map.in");
+	assert_eq!(MAP.len(),1200);
+	for (i,(key,&value)) in MAP.iter().enumerate(){
+		assert_eq!(value,i as u32);
+		assert_eq!(key.as_str(),format!("key{i}"));
+		assert_eq!(MAP.get(key),Some(&value));
+	}
+	assert_eq!(MAP.get("key1200"),None);
+}
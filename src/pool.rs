@@ -0,0 +1,222 @@
+use std::mem::ManuallyDrop;
+use std::sync::{Arc,Weak};
+use hashbrown::HashTable;
+use parking_lot::Mutex;
+use crate::ornaments::GetHash;
+use crate::hash_str::HashStr;
+use crate::hash::{whichshard,NUM_BINS};
+
+/// A reference counted HashStr owned by the global string pool.
+/// The string is freed once the last PooledHashStr pointing to it is dropped.
+/// While any PooledHashStr is alive, interning the same string
+/// returns a PooledHashStr pointing to the same HashStr.
+pub struct PooledHashStr(ManuallyDrop<Arc<Box<HashStr>>>);
+
+struct PoolEntry{
+	hash:u64,
+	weak:Weak<Box<HashStr>>,
+}
+
+/// The type used for the global string pool.
+/// Unlike [`Bins`](crate::Bins), the pool only holds weak references
+/// to the interned strings, so unused strings are reclaimed.
+/// Shards are chosen from the middle bits of the hash, which the tables do not use.
+#[repr(transparent)]
+pub struct PoolBins([Mutex<HashTable<PoolEntry>>; NUM_BINS]);
+
+lazy_static::lazy_static!{
+	static ref STRING_POOL:PoolBins =
+	PoolBins(core::array::from_fn(|_|Mutex::new(HashTable::new())));
+}
+
+#[inline]
+pub fn get_pool()->&'static PoolBins{
+	&STRING_POOL
+}
+
+fn get_entry_hash(entry:&PoolEntry)->u64{
+	entry.hash
+}
+
+// All strong reference decrements and weak upgrades happen while holding
+// the bin lock, so every entry in a bin is alive while the lock is held.
+impl PoolBins{
+	/// Get a string from the global pool.
+	#[inline]
+	pub fn get(&self,index:impl GetHash+AsRef<str>)->Option<PooledHashStr>{
+		let (hash,str)=(index.get_hash(),index.as_ref());
+		let bin=self.0[whichshard(hash)].lock();
+		let entry=bin.find(hash,|entry|entry_eq(entry,hash,str))?;
+		entry.weak.upgrade().map(|arc|PooledHashStr(ManuallyDrop::new(arc)))
+	}
+	/// Intern a string into the global pool, utilizing the precalculated hash if possible.
+	/// This will return an existing HashStr if one is alive, or allocate a new one otherwise.
+	#[inline]
+	pub fn intern(&self,index:impl GetHash+AsRef<str>)->PooledHashStr{
+		let (hash,str)=(index.get_hash(),index.as_ref());
+		let mut bin=self.0[whichshard(hash)].lock();
+		let entry=bin.entry(
+			hash,
+			|entry|entry_eq(entry,hash,str),
+			get_entry_hash,
+		).or_insert_with(||PoolEntry{
+			hash,
+			weak:Weak::new(),
+		}).into_mut();
+		let arc=match entry.weak.upgrade(){
+			Some(arc)=>arc,
+			None=>{
+				let arc=Arc::new(HashStr::boxed_str_with_hash(hash,str));
+				entry.weak=Arc::downgrade(&arc);
+				arc
+			},
+		};
+		PooledHashStr(ManuallyDrop::new(arc))
+	}
+	/// Number of live strings in the global pool.
+	#[inline]
+	pub fn len(&self)->usize{
+		self.0.iter().map(|bin|bin.lock().len()).sum()
+	}
+	#[inline]
+	pub fn is_empty(&self)->bool{
+		self.0.iter().all(|bin|bin.lock().is_empty())
+	}
+}
+
+#[inline]
+fn entry_eq(entry:&PoolEntry,hash:u64,str:&str)->bool{
+	// compare the hash first to avoid upgrading, and a freshly inserted entry has no string yet
+	entry.hash==hash&&entry.weak.upgrade().is_some_and(|arc|arc.as_str()==str)
+}
+
+impl PooledHashStr{
+	#[inline]
+	pub fn as_hash_str(&self)->&HashStr{
+		&self.0
+	}
+	/// Two PooledHashStrs with the same contents always point to the same HashStr.
+	#[inline]
+	pub fn ptr_eq(&self,other:&Self)->bool{
+		Arc::ptr_eq(&self.0,&other.0)
+	}
+}
+
+impl Clone for PooledHashStr{
+	#[inline]
+	fn clone(&self)->Self{
+		PooledHashStr(ManuallyDrop::new(Arc::clone(&self.0)))
+	}
+}
+
+impl Drop for PooledHashStr{
+	fn drop(&mut self){
+		let hash=self.0.precomputed_hash();
+		let mut bin=STRING_POOL.0[whichshard(hash)].lock();
+		// SAFETY: self.0 is never used again
+		let arc=unsafe{ManuallyDrop::take(&mut self.0)};
+		if Arc::strong_count(&arc)==1{
+			// this is the last reference, remove the entry before the string is freed
+			if let Ok(entry)=bin.find_entry(hash,|entry|core::ptr::addr_eq(entry.weak.as_ptr(),Arc::as_ptr(&arc))){
+				entry.remove();
+			}
+		}
+		// decrement while still holding the lock
+		drop(arc);
+	}
+}
+
+impl core::ops::Deref for PooledHashStr{
+	type Target=HashStr;
+	#[inline]
+	fn deref(&self)->&Self::Target{
+		self.as_hash_str()
+	}
+}
+impl AsRef<HashStr> for PooledHashStr{
+	#[inline]
+	fn as_ref(&self)->&HashStr{
+		self.as_hash_str()
+	}
+}
+impl AsRef<str> for PooledHashStr{
+	#[inline]
+	fn as_ref(&self)->&str{
+		self.as_str()
+	}
+}
+impl core::borrow::Borrow<HashStr> for PooledHashStr{
+	#[inline]
+	fn borrow(&self)->&HashStr{
+		self.as_hash_str()
+	}
+}
+impl core::fmt::Debug for PooledHashStr{
+	#[inline]
+	fn fmt(&self,f:&mut core::fmt::Formatter<'_>)->core::fmt::Result{
+		self.as_hash_str().fmt(f)
+	}
+}
+impl core::fmt::Display for PooledHashStr{
+	#[inline]
+	fn fmt(&self,f:&mut core::fmt::Formatter<'_>)->core::fmt::Result{
+		f.write_str(self.as_str())
+	}
+}
+impl PartialEq for PooledHashStr{
+	#[inline]
+	fn eq(&self,other:&Self)->bool{
+		self.ptr_eq(other)
+	}
+}
+impl Eq for PooledHashStr{}
+impl core::hash::Hash for PooledHashStr{
+	#[inline]
+	fn hash<H:core::hash::Hasher>(&self,state:&mut H){
+		self.as_hash_str().hash(state)
+	}
+}
+impl GetHash for PooledHashStr{
+	#[inline]
+	fn get_hash(&self)->u64{
+		self.precomputed_hash()
+	}
+}
+impl GetHash for &PooledHashStr{
+	#[inline]
+	fn get_hash(&self)->u64{
+		self.precomputed_hash()
+	}
+}
+
+#[test]
+fn test_pool(){
+	let pool=get_pool();
+	let a=pool.intern("pool_test_str");
+	let b=pool.intern("pool_test_str");
+	assert!(a.ptr_eq(&b));
+	assert_eq!(a.as_str(),"pool_test_str");
+	let c=pool.get(&a).unwrap();
+	assert!(a.ptr_eq(&c));
+
+	// the string is reclaimed once all references are dropped
+	drop(a);
+	drop(b);
+	assert!(pool.get("pool_test_str").is_some());
+	drop(c);
+	assert!(pool.get("pool_test_str").is_none());
+
+	// interning again allocates a new string
+	let d=pool.intern("pool_test_str");
+	assert_eq!(pool.get("pool_test_str"),Some(d));
+}
+
+#[test]
+fn test_pool_shards(){
+	// within a shard the bucket and tag bits of the hashes still vary
+	let strs:Vec<PooledHashStr>=(0..4096).map(|i|get_pool().intern(format!("pool_shard{i}").as_str())).collect();
+	let shard=get_pool().0[whichshard(strs[0].precomputed_hash())].lock();
+	let low:std::collections::HashSet<u64>=shard.iter().map(|entry|entry.hash&0x3f).collect();
+	let top:std::collections::HashSet<u64>=shard.iter().map(|entry|entry.hash>>57).collect();
+	assert!(low.len()>16&&top.len()>16);
+}
@@ -0,0 +1,409 @@
+use core::marker::PhantomData;
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use serde::{Serialize,Serializer,Deserialize,Deserializer};
+use serde::de::{Error,SeqAccess,Visitor};
+use crate::hash::make_hash;
+use crate::hash_str::HashStr;
+use super::hash_str::{deserialize_hash_str,split_hash_str};
+
+// Field adapters used by the `as_str`, `hash_prefixed` and `global` modules.
+// The encoding is chosen by the marker type, and containers of
+// adaptable types are adapted element by element.
+
+#[doc(hidden)]
+pub enum AsStr{}
+#[doc(hidden)]
+pub enum HashPrefixed{}
+#[doc(hidden)]
+pub enum Global{}
+
+/// A type which can be serialized with the encoding `E`.
+#[doc(hidden)]
+pub trait SerializeAdapter<E>{
+	fn serialize_with<S:Serializer>(&self,serializer:S)->Result<S::Ok,S::Error>;
+}
+
+/// A type which can be deserialized with the encoding `E`.
+#[doc(hidden)]
+pub trait DeserializeAdapter<'de,E>:Sized{
+	fn deserialize_with<D:Deserializer<'de>>(deserializer:D)->Result<Self,D::Error>;
+}
+
+/// Serialize a value with the encoding `E`.
+pub(crate) struct Adapted<'a,E,T:?Sized>(&'a T,PhantomData<E>);
+impl<E,T:SerializeAdapter<E>+?Sized> Serialize for Adapted<'_,E,T>{
+	#[inline]
+	fn serialize<S:Serializer>(&self,serializer:S)->Result<S::Ok,S::Error>{
+		self.0.serialize_with(serializer)
+	}
+}
+#[inline]
+pub(crate) fn adapted<E,T:?Sized>(value:&T)->Adapted<'_,E,T>{
+	Adapted(value,PhantomData)
+}
+
+/// Deserialize a value with the encoding `E`.
+pub(crate) struct Adapt<E,T>(T,PhantomData<E>);
+impl<'de,E,T:DeserializeAdapter<'de,E>> Deserialize<'de> for Adapt<E,T>{
+	#[inline]
+	fn deserialize<D:Deserializer<'de>>(deserializer:D)->Result<Self,D::Error>{
+		Ok(Adapt(T::deserialize_with(deserializer)?,PhantomData))
+	}
+}
+impl<E,T:PartialEq> PartialEq for Adapt<E,T>{
+	#[inline]
+	fn eq(&self,other:&Self)->bool{
+		self.0==other.0
+	}
+}
+impl<E,T:Eq> Eq for Adapt<E,T>{}
+impl<E,T:PartialOrd> PartialOrd for Adapt<E,T>{
+	#[inline]
+	fn partial_cmp(&self,other:&Self)->Option<core::cmp::Ordering>{
+		self.0.partial_cmp(&other.0)
+	}
+}
+impl<E,T:Ord> Ord for Adapt<E,T>{
+	#[inline]
+	fn cmp(&self,other:&Self)->core::cmp::Ordering{
+		self.0.cmp(&other.0)
+	}
+}
+impl<E,T:core::hash::Hash> core::hash::Hash for Adapt<E,T>{
+	#[inline]
+	fn hash<H:core::hash::Hasher>(&self,state:&mut H){
+		self.0.hash(state)
+	}
+}
+
+/// Read a str, calculating the hash.
+pub(crate) struct StrVisitor<F>(pub(crate) F);
+impl<T,F:FnOnce(u64,&str)->T> Visitor<'_> for StrVisitor<F>{
+	type Value=T;
+	fn expecting(&self,formatter:&mut core::fmt::Formatter)->core::fmt::Result{
+		write!(formatter,"a string")
+	}
+	fn visit_str<Err:Error>(self,v:&str)->Result<Self::Value,Err>{
+		Ok((self.0)(make_hash(v),v))
+	}
+}
+
+/// Read hash prefixed bytes, borrowed or owned, reusing the hash.  Sequences
+/// of bytes are accepted for formats without a bytes type such as JSON, and
+/// plain strings are accepted by calculating the hash.
+pub(crate) struct HashPrefixedVisitor<F>(pub(crate) F);
+impl<'de,T,F:FnOnce(u64,&str)->T> Visitor<'de> for HashPrefixedVisitor<F>{
+	type Value=T;
+	fn expecting(&self,formatter:&mut core::fmt::Formatter)->core::fmt::Result{
+		write!(formatter,"Hash Str")
+	}
+	fn visit_bytes<Err:Error>(self,v:&[u8])->Result<Self::Value,Err>{
+		let (hash,str)=split_hash_str(v)?;
+		Ok((self.0)(hash,str))
+	}
+	fn visit_str<Err:Error>(self,v:&str)->Result<Self::Value,Err>{
+		Ok((self.0)(make_hash(v),v))
+	}
+	fn visit_seq<A:SeqAccess<'de>>(self,seq:A)->Result<Self::Value,A::Error>{
+		self.visit_bytes(&collect_bytes(seq)?)
+	}
+}
+
+/// Collect a sequence of bytes.
+pub(crate) fn collect_bytes<'de,A:SeqAccess<'de>>(mut seq:A)->Result<Vec<u8>,A::Error>{
+	let mut bytes=Vec::with_capacity(seq.size_hint().unwrap_or(0));
+	while let Some(byte)=seq.next_element()?{
+		bytes.push(byte);
+	}
+	Ok(bytes)
+}
+
+// === Encodings ===
+
+macro_rules! impl_serialize{
+	($encoding:ty,|$value:ident,$serializer:ident|$body:expr)=>{
+		impl SerializeAdapter<$encoding> for HashStr{
+			#[inline]
+			fn serialize_with<S:Serializer>(&self,$serializer:S)->Result<S::Ok,S::Error>{
+				let $value=self;
+				$body
+			}
+		}
+		impl SerializeAdapter<$encoding> for &HashStr{
+			#[inline]
+			fn serialize_with<S:Serializer>(&self,serializer:S)->Result<S::Ok,S::Error>{
+				SerializeAdapter::<$encoding>::serialize_with(*self,serializer)
+			}
+		}
+		impl SerializeAdapter<$encoding> for Box<HashStr>{
+			#[inline]
+			fn serialize_with<S:Serializer>(&self,serializer:S)->Result<S::Ok,S::Error>{
+				SerializeAdapter::<$encoding>::serialize_with(&**self,serializer)
+			}
+		}
+	};
+}
+impl_serialize!(AsStr,|value,serializer|serializer.serialize_str(value.as_str()));
+impl_serialize!(HashPrefixed,|value,serializer|serializer.serialize_bytes(value.as_hash_str_bytes()));
+impl_serialize!(Global,|value,serializer|serializer.serialize_str(value.as_str()));
+
+impl<'de> DeserializeAdapter<'de,AsStr> for Box<HashStr>{
+	#[inline]
+	fn deserialize_with<D:Deserializer<'de>>(deserializer:D)->Result<Self,D::Error>{
+		deserializer.deserialize_str(StrVisitor(HashStr::boxed_str_with_hash))
+	}
+}
+impl<'de> DeserializeAdapter<'de,HashPrefixed> for Box<HashStr>{
+	#[inline]
+	fn deserialize_with<D:Deserializer<'de>>(deserializer:D)->Result<Self,D::Error>{
+		deserialize_hash_str(deserializer,HashPrefixedVisitor(HashStr::boxed_str_with_hash))
+	}
+}
+#[cfg(feature="global")]
+impl<'de> DeserializeAdapter<'de,AsStr> for &'static HashStr{
+	#[inline]
+	fn deserialize_with<D:Deserializer<'de>>(deserializer:D)->Result<Self,D::Error>{
+		deserializer.deserialize_str(super::global::HashStrVisitorGlobalFromStr)
+	}
+}
+#[cfg(feature="global")]
+impl<'de> DeserializeAdapter<'de,HashPrefixed> for &'static HashStr{
+	#[inline]
+	fn deserialize_with<D:Deserializer<'de>>(deserializer:D)->Result<Self,D::Error>{
+		deserialize_hash_str(deserializer,super::global::HashStrVisitorGlobalFromHashStr)
+	}
+}
+#[cfg(feature="global")]
+impl<'de> DeserializeAdapter<'de,Global> for &'static HashStr{
+	#[inline]
+	fn deserialize_with<D:Deserializer<'de>>(deserializer:D)->Result<Self,D::Error>{
+		deserializer.deserialize_str(super::global::HashStrVisitorGlobalFromStr)
+	}
+}
+
+// === Containers ===
+
+impl<E,T:SerializeAdapter<E>> SerializeAdapter<E> for Option<T>{
+	#[inline]
+	fn serialize_with<S:Serializer>(&self,serializer:S)->Result<S::Ok,S::Error>{
+		self.as_ref().map(adapted::<E,T>).serialize(serializer)
+	}
+}
+impl<'de,E,T:DeserializeAdapter<'de,E>> DeserializeAdapter<'de,E> for Option<T>{
+	#[inline]
+	fn deserialize_with<D:Deserializer<'de>>(deserializer:D)->Result<Self,D::Error>{
+		Ok(Option::<Adapt<E,T>>::deserialize(deserializer)?.map(|Adapt(value,_)|value))
+	}
+}
+
+impl<E,T:SerializeAdapter<E>> SerializeAdapter<E> for [T]{
+	#[inline]
+	fn serialize_with<S:Serializer>(&self,serializer:S)->Result<S::Ok,S::Error>{
+		serializer.collect_seq(self.iter().map(adapted::<E,T>))
+	}
+}
+impl<E,T:SerializeAdapter<E>> SerializeAdapter<E> for Vec<T>{
+	#[inline]
+	fn serialize_with<S:Serializer>(&self,serializer:S)->Result<S::Ok,S::Error>{
+		SerializeAdapter::<E>::serialize_with(self.as_slice(),serializer)
+	}
+}
+impl<'de,E,T:DeserializeAdapter<'de,E>> DeserializeAdapter<'de,E> for Vec<T>{
+	#[inline]
+	fn deserialize_with<D:Deserializer<'de>>(deserializer:D)->Result<Self,D::Error>{
+		Ok(Vec::<Adapt<E,T>>::deserialize(deserializer)?.into_iter().map(|Adapt(value,_)|value).collect())
+	}
+}
+
+impl<E,K:SerializeAdapter<E>,V:Serialize> SerializeAdapter<E> for BTreeMap<K,V>{
+	#[inline]
+	fn serialize_with<S:Serializer>(&self,serializer:S)->Result<S::Ok,S::Error>{
+		serializer.collect_map(self.iter().map(|(key,value)|(adapted::<E,K>(key),value)))
+	}
+}
+impl<'de,E,K:DeserializeAdapter<'de,E>+Ord,V:Deserialize<'de>> DeserializeAdapter<'de,E> for BTreeMap<K,V>{
+	#[inline]
+	fn deserialize_with<D:Deserializer<'de>>(deserializer:D)->Result<Self,D::Error>{
+		Ok(BTreeMap::<Adapt<E,K>,V>::deserialize(deserializer)?.into_iter().map(|(Adapt(key,_),value)|(key,value)).collect())
+	}
+}
+
+#[cfg(feature="std")]
+impl<E,K:SerializeAdapter<E>,V:Serialize,H> SerializeAdapter<E> for std::collections::HashMap<K,V,H>{
+	#[inline]
+	fn serialize_with<S:Serializer>(&self,serializer:S)->Result<S::Ok,S::Error>{
+		serializer.collect_map(self.iter().map(|(key,value)|(adapted::<E,K>(key),value)))
+	}
+}
+#[cfg(feature="std")]
+impl<'de,E,K,V,H> DeserializeAdapter<'de,E> for std::collections::HashMap<K,V,H>
+	where
+		K:DeserializeAdapter<'de,E>+Eq+core::hash::Hash,
+		V:Deserialize<'de>,
+		H:core::hash::BuildHasher+Default,
+{
+	#[inline]
+	fn deserialize_with<D:Deserializer<'de>>(deserializer:D)->Result<Self,D::Error>{
+		// keys are adapted one at a time into the final map
+		struct MapVisitor<E,K,V,H>(PhantomData<(E,K,V,H)>);
+		impl<'de,E,K,V,H> Visitor<'de> for MapVisitor<E,K,V,H>
+			where
+				K:DeserializeAdapter<'de,E>+Eq+core::hash::Hash,
+				V:Deserialize<'de>,
+				H:core::hash::BuildHasher+Default,
+		{
+			type Value=std::collections::HashMap<K,V,H>;
+			fn expecting(&self,formatter:&mut core::fmt::Formatter)->core::fmt::Result{
+				write!(formatter,"a map")
+			}
+			fn visit_map<A:serde::de::MapAccess<'de>>(self,mut map:A)->Result<Self::Value,A::Error>{
+				let mut result=std::collections::HashMap::with_capacity_and_hasher(map.size_hint().unwrap_or(0),H::default());
+				while let Some((Adapt(key,_),value))=map.next_entry::<Adapt<E,K>,V>()?{
+					result.insert(key,value);
+				}
+				Ok(result)
+			}
+		}
+		deserializer.deserialize_map(MapVisitor(PhantomData))
+	}
+}
+
+#[cfg(feature="std")]
+impl<E,K:SerializeAdapter<E>,H> SerializeAdapter<E> for std::collections::HashSet<K,H>{
+	#[inline]
+	fn serialize_with<S:Serializer>(&self,serializer:S)->Result<S::Ok,S::Error>{
+		serializer.collect_seq(self.iter().map(adapted::<E,K>))
+	}
+}
+#[cfg(feature="std")]
+impl<'de,E,K,H> DeserializeAdapter<'de,E> for std::collections::HashSet<K,H>
+	where
+		K:DeserializeAdapter<'de,E>+Eq+core::hash::Hash,
+		H:core::hash::BuildHasher+Default,
+{
+	#[inline]
+	fn deserialize_with<D:Deserializer<'de>>(deserializer:D)->Result<Self,D::Error>{
+		Ok(Vec::<Adapt<E,K>>::deserialize(deserializer)?.into_iter().map(|Adapt(value,_)|value).collect())
+	}
+}
+
+#[cfg(feature="std")]
+impl<'a,E,V:Serialize> SerializeAdapter<E> for crate::collections::HashStrMap<'a,V> where &'a HashStr:SerializeAdapter<E>{
+	#[inline]
+	fn serialize_with<S:Serializer>(&self,serializer:S)->Result<S::Ok,S::Error>{
+		SerializeAdapter::<E>::serialize_with(&**self,serializer)
+	}
+}
+#[cfg(feature="std")]
+impl<'a,'de,E,V:Deserialize<'de>> DeserializeAdapter<'de,E> for crate::collections::HashStrMap<'a,V> where &'a HashStr:DeserializeAdapter<'de,E>{
+	#[inline]
+	fn deserialize_with<D:Deserializer<'de>>(deserializer:D)->Result<Self,D::Error>{
+		Ok(Self::from_inner(DeserializeAdapter::<E>::deserialize_with(deserializer)?))
+	}
+}
+#[cfg(feature="std")]
+impl<'a,E> SerializeAdapter<E> for crate::collections::HashStrSet<'a> where &'a HashStr:SerializeAdapter<E>{
+	#[inline]
+	fn serialize_with<S:Serializer>(&self,serializer:S)->Result<S::Ok,S::Error>{
+		SerializeAdapter::<E>::serialize_with(&**self,serializer)
+	}
+}
+#[cfg(feature="std")]
+impl<'a,'de,E> DeserializeAdapter<'de,E> for crate::collections::HashStrSet<'a> where &'a HashStr:DeserializeAdapter<'de,E>{
+	#[inline]
+	fn deserialize_with<D:Deserializer<'de>>(deserializer:D)->Result<Self,D::Error>{
+		Ok(Self::from_inner(DeserializeAdapter::<E>::deserialize_with(deserializer)?))
+	}
+}
+
+#[test]
+fn test_adapters(){
+	use crate::hash_str::HashStr;
+	#[derive(Debug,PartialEq,serde::Serialize,serde::Deserialize)]
+	struct Fields{
+		#[serde(with="super::as_str")]
+		name:Box<HashStr>,
+		#[serde(with="super::as_str")]
+		alias:Option<Box<HashStr>>,
+		#[serde(with="super::hash_prefixed")]
+		tags:Vec<Box<HashStr>>,
+		#[serde(with="super::hash_prefixed")]
+		counts:BTreeMap<Box<HashStr>,u32>,
+	}
+	let fields=Fields{
+		name:HashStr::anonymous("name".to_owned()),
+		alias:None,
+		tags:vec![HashStr::anonymous("a".to_owned()),HashStr::anonymous("".to_owned())],
+		counts:[(HashStr::anonymous("count".to_owned()),3)].into_iter().collect(),
+	};
+	let bytes=postcard::to_allocvec(&fields).unwrap();
+	let decoded:Fields=postcard::from_bytes(&bytes).unwrap();
+	assert_eq!(decoded,fields);
+	for (decoded,original) in decoded.tags.iter().zip(&fields.tags){
+		assert_eq!(decoded.precomputed_hash(),original.precomputed_hash());
+	}
+	// hash prefixed bytes become arrays in json
+	let json=serde_json::to_string(&Fields{counts:BTreeMap::new(),..fields}).unwrap();
+	assert!(json.starts_with(r#"{"name":"name","alias":null,"tags":[["#));
+	let decoded:Fields=serde_json::from_str(&json).unwrap();
+	assert_eq!(decoded.tags[0].as_str(),"a");
+
+	// hash prefixed bytes which are too short
+	assert!(postcard::from_bytes::<Fields>(&[4,b'n',b'a',b'm',b'e',0,1,1,0]).is_err());
+}
+
+#[cfg(feature="global")]
+#[test]
+fn test_global_adapters(){
+	use std::collections::HashMap;
+	use crate::global::get_cache;
+	#[derive(serde::Serialize,serde::Deserialize)]
+	struct Fields{
+		#[serde(with="super::global")]
+		kind:&'static HashStr,
+		#[serde(with="super::global")]
+		counts:HashMap<&'static HashStr,u32>,
+		#[serde(with="super::hash_prefixed")]
+		key:Option<&'static HashStr>,
+	}
+	let hs=get_cache().intern("serde_adapter");
+	let fields=Fields{
+		kind:hs,
+		counts:[(hs,1)].into_iter().collect(),
+		key:Some(hs),
+	};
+	let json=serde_json::to_string(&fields).unwrap();
+	let decoded:Fields=serde_json::from_str(&json).unwrap();
+	assert!(core::ptr::addr_eq(decoded.kind,hs));
+	assert_eq!(decoded.counts.get(hs),Some(&1));
+	assert!(core::ptr::addr_eq(decoded.key.unwrap(),hs));
+
+	let bytes=postcard::to_allocvec(&fields).unwrap();
+	let decoded:Fields=postcard::from_bytes(&bytes).unwrap();
+	assert!(core::ptr::addr_eq(decoded.kind,hs));
+
+	#[derive(serde::Serialize,serde::Deserialize)]
+	struct Collections{
+		#[serde(with="super::as_str")]
+		name:&'static HashStr,
+		#[serde(with="super::as_str")]
+		counts:crate::collections::HashStrMap<'static,u32>,
+		#[serde(with="super::hash_prefixed")]
+		tags:crate::collections::HashStrSet<'static>,
+	}
+	let collections=Collections{
+		name:hs,
+		counts:[(hs,2)].into_iter().collect(),
+		tags:[hs].into_iter().collect(),
+	};
+	let json=serde_json::to_string(&collections).unwrap();
+	assert!(json.starts_with(r#"{"name":"serde_adapter","counts":{"serde_adapter":2},"tags":[["#));
+	let decoded:Collections=serde_json::from_str(&json).unwrap();
+	assert!(core::ptr::addr_eq(decoded.name,hs));
+	assert_eq!(decoded.counts.get_str("serde_adapter"),Some(&2));
+	assert!(core::ptr::addr_eq(decoded.tags.get_str("serde_adapter").unwrap(),hs));
+	let decoded:Collections=serde_json::from_str(r#"{"name":"serde_adapter","counts":{},"tags":["serde_adapter"]}"#).unwrap();
+	assert!(core::ptr::addr_eq(decoded.tags.get_str("serde_adapter").unwrap(),hs));
+}
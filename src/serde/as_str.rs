@@ -0,0 +1,31 @@
+//! Serialize HashStrs as plain strings, calculating the hash when deserializing.
+//! Deserializes into `Box<HashStr>`, or interns `&'static HashStr` into the
+//! global cache like `serde::global`.  Also supports `Option`, `Vec`,
+//! sets and map keys of these types, including `HashStrMap` and `HashStrSet`.
+//!
+//! ```rust
+//! use hash_str::HashStr;
+//! use serde::{Serialize,Deserialize};
+//!
+//! #[derive(Serialize,Deserialize)]
+//! struct Config{
+//!     #[serde(with="hash_str::serde::as_str")]
+//!     name:Box<HashStr>,
+//!     #[serde(with="hash_str::serde::as_str")]
+//!     tags:Vec<Box<HashStr>>,
+//! }
+//!
+//! let config:Config=serde_json::from_str(r#"{"name":"bruh","tags":["a","b"]}"#).unwrap();
+//! assert_eq!(config.name.as_str(),"bruh");
+//! assert_eq!(serde_json::to_string(&config).unwrap(),r#"{"name":"bruh","tags":["a","b"]}"#);
+//! ```
+use serde::{Serializer,Deserializer};
+use super::adapter::{AsStr,SerializeAdapter,DeserializeAdapter};
+
+pub fn serialize<T:SerializeAdapter<AsStr>+?Sized,S:Serializer>(value:&T,serializer:S)->Result<S::Ok,S::Error>{
+	value.serialize_with(serializer)
+}
+
+pub fn deserialize<'de,T:DeserializeAdapter<'de,AsStr>,D:Deserializer<'de>>(deserializer:D)->Result<T,D::Error>{
+	T::deserialize_with(deserializer)
+}
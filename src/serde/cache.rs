@@ -1,8 +1,7 @@
 use serde::de::{Error,Visitor};
 use crate::cache::{HashStrCache,HashStrHost};
-use crate::ornaments::GetHash;
 use crate::hash_str::HashStr;
-use super::HashStrVisitorZeroCopy;
+use super::hash_str::split_hash_str;
 
 /// Read hash value and str and intern into specified cache.
 pub struct HashStrVisitorHostedFromHashStr<'host>{
@@ -12,12 +11,11 @@ pub struct HashStrVisitorHostedFromHashStr<'host>{
 
 impl<'host> Visitor<'_> for HashStrVisitorHostedFromHashStr<'host>{
 	type Value=&'host HashStr;
-	fn expecting(&self,formatter:&mut std::fmt::Formatter)->std::fmt::Result{
+	fn expecting(&self,formatter:&mut core::fmt::Formatter)->core::fmt::Result{
 		write!(formatter,"Hash Str")
 	}
-	fn visit_borrowed_bytes<E:Error>(self,v:&[u8])->Result<Self::Value,E>{
-		let h=HashStrVisitorZeroCopy.visit_bytes(v)?;
-		let (hash,str)=(h.get_hash(),h.as_str());
+	fn visit_bytes<E:Error>(self,v:&[u8])->Result<Self::Value,E>{
+		let (hash,str)=split_hash_str(v)?;
 		Ok(self.cache.intern_str_with_hash(||self.host.alloc_str_with_hash(hash,str),hash,str))
 	}
 }
@@ -30,10 +28,10 @@ pub struct HashStrVisitorHostedFromStr<'host>{
 
 impl<'host> Visitor<'_> for HashStrVisitorHostedFromStr<'host>{
 	type Value=&'host HashStr;
-	fn expecting(&self,formatter:&mut std::fmt::Formatter)->std::fmt::Result{
+	fn expecting(&self,formatter:&mut core::fmt::Formatter)->core::fmt::Result{
 		write!(formatter,"Hash Str")
 	}
 	fn visit_str<E:Error>(self,v:&str)->Result<Self::Value,E>{
-		Ok(self.cache.intern_with(&self.host,v))
+		Ok(self.cache.intern_with(self.host,v))
 	}
 }
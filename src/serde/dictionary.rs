@@ -0,0 +1,670 @@
+//! Dictionary encoding for documents which repeat the same HashStrs.
+//!
+//! Fields marked with `#[serde(with="hash_str::serde::dictionary")]` are
+//! serialized as an index into a string table.  Wrap the document in
+//! [`DictEncoded`] to write the string table once followed by the document,
+//! and read it back with `decode_global` or [`decode_with`].  Decoded fields
+//! borrow from the host for the lifetime of the deserializer input, or use
+//! `dictionary::global` for `&'static HashStr` fields.
+//!
+//! Dictionary encoded fields cannot be read through `#[serde(flatten)]` or
+//! untagged enums, which buffer the input before it reaches the field.
+//!
+//! ```rust
+//! use hash_str::{HashStr,HashStrHost,HashStrCache};
+//! use hash_str::serde::dictionary::{self,DictEncoded};
+//! use serde::{Serialize,Deserialize};
+//!
+//! #[derive(Serialize,Deserialize)]
+//! struct Record<'a>{
+//!     #[serde(borrow,with="dictionary")]
+//!     key:&'a HashStr,
+//!     value:u32,
+//! }
+//!
+//! let records:Vec<Record>=(0..100).map(|value|Record{key:hash_str::hstr!("repeated key"),value}).collect();
+//! let bytes=postcard::to_allocvec(&DictEncoded(&records)).unwrap();
+//!
+//! let host=HashStrHost::new();
+//! let mut cache=HashStrCache::new();
+//! let decoded:Vec<Record>=dictionary::decode_with(&host,&mut cache,&mut postcard::Deserializer::from_bytes(&bytes)).unwrap();
+//! assert_eq!(decoded[99].key.as_str(),"repeated key");
+//! ```
+use core::cell::RefCell;
+use core::marker::PhantomData;
+use core::hash::BuildHasherDefault;
+use std::collections::HashMap;
+use serde::{Serialize,Serializer,Deserialize,Deserializer};
+use serde::ser::{self,SerializeTuple};
+use serde::de::{self,DeserializeSeed,EnumAccess,MapAccess,SeqAccess,VariantAccess,Visitor};
+use crate::hash::IdentityHasher;
+use crate::hash_str::HashStr;
+use super::adapter::HashPrefixedVisitor;
+use super::hash_str::{deserialize_hash_str,HashStrVisitorZeroCopy};
+
+/// Strings collected while encoding, mapped to their index in the string table.
+type Encoder=HashMap<Box<HashStr>,u32,BuildHasherDefault<IdentityHasher>>;
+
+std::thread_local!{
+	static ENCODER:RefCell<Option<Encoder>>=const{RefCell::new(None)};
+}
+
+/// Restores the previous thread local state when dropped, even when unwinding.
+struct Restore<T:'static>{
+	key:&'static std::thread::LocalKey<RefCell<Option<T>>>,
+	previous:Option<T>,
+}
+impl<T> Restore<T>{
+	fn replace(key:&'static std::thread::LocalKey<RefCell<Option<T>>>,value:T)->Self{
+		let previous=key.with(|cell|cell.replace(Some(value)));
+		Restore{key,previous}
+	}
+	/// Restore the previous state now, returning the current state.
+	fn take(mut self)->Option<T>{
+		let previous=self.previous.take();
+		let current=self.key.with(|cell|cell.replace(previous));
+		core::mem::forget(self);
+		current
+	}
+}
+impl<T> Drop for Restore<T>{
+	fn drop(&mut self){
+		let previous=self.previous.take();
+		self.key.with(|cell|cell.replace(previous));
+	}
+}
+
+/// Serialize a HashStr as an index into the string table of the enclosing [`DictEncoded`].
+pub fn serialize<S:Serializer>(hash_str:&HashStr,serializer:S)->Result<S::Ok,S::Error>{
+	let index=ENCODER.with(|encoder|{
+		let mut encoder=encoder.borrow_mut();
+		let encoder=encoder.as_mut()?;
+		if let Some(&index)=encoder.get(hash_str){
+			return Some(index);
+		}
+		let index=encoder.len() as u32;
+		encoder.insert(HashStr::boxed_str_with_hash(hash_str.precomputed_hash(),hash_str.as_str()),index);
+		Some(index)
+	});
+	match index{
+		Some(index)=>serializer.serialize_u32(index),
+		None=>Err(ser::Error::custom("dictionary encoded HashStr serialized outside of DictEncoded")),
+	}
+}
+
+/// Deserialize an index into the string table of the enclosing `decode_global` or [`decode_with`],
+/// borrowing the HashStr for the lifetime of the deserializer input.
+pub fn deserialize<'de,D:Deserializer<'de>>(deserializer:D)->Result<&'de HashStr,D::Error>{
+	deserializer.deserialize_newtype_struct(MARKER,FieldVisitor(HashStrVisitorZeroCopy))
+}
+
+/// Dictionary encoded fields of type `&'static HashStr`.  Entries of the
+/// string table are interned into the global cache as they are read.
+#[cfg(feature="global")]
+pub mod global{
+	use serde::Deserializer;
+	use crate::hash_str::HashStr;
+	pub use super::serialize;
+
+	pub fn deserialize<'de,D:Deserializer<'de>>(deserializer:D)->Result<&'static HashStr,D::Error>{
+		deserializer.deserialize_newtype_struct(super::MARKER,super::FieldVisitor(crate::serde::HashStrVisitorGlobalFromHashStr))
+	}
+}
+
+/// Serializes the string table of all dictionary encoded HashStrs in the
+/// value, followed by the value.  The value is serialized twice, first to
+/// collect the strings and then to write it.
+pub struct DictEncoded<'a,T:?Sized>(pub &'a T);
+
+impl<T:Serialize+?Sized> Serialize for DictEncoded<'_,T>{
+	fn serialize<S:Serializer>(&self,serializer:S)->Result<S::Ok,S::Error>{
+		let restore=Restore::replace(&ENCODER,Encoder::default());
+		self.0.serialize(Collect).map_err(ser::Error::custom)?;
+		let encoder=restore.take().unwrap_or_default();
+		let mut table:Vec<(&Box<HashStr>,&u32)>=encoder.iter().collect();
+		table.sort_unstable_by_key(|&(_,&index)|index);
+		let table:Vec<&HashStr>=table.into_iter().map(|(hash_str,_)|&**hash_str).collect();
+
+		let mut tuple=serializer.serialize_tuple(2)?;
+		tuple.serialize_element(&table)?;
+		// serialize the value with the same encoder so every string is found
+		let restore=Restore::replace(&ENCODER,encoder);
+		tuple.serialize_element(self.0)?;
+		drop(restore);
+		tuple.end()
+	}
+}
+
+/// Interns each string of the table, reusing the saved hash.
+struct TableSeed<F>(F);
+
+impl<'de,F:FnMut(u64,&str)->&'de HashStr> DeserializeSeed<'de> for TableSeed<F>{
+	type Value=Vec<&'de HashStr>;
+	fn deserialize<D:Deserializer<'de>>(self,deserializer:D)->Result<Self::Value,D::Error>{
+		deserializer.deserialize_seq(self)
+	}
+}
+
+impl<'de,F:FnMut(u64,&str)->&'de HashStr> Visitor<'de> for TableSeed<F>{
+	type Value=Vec<&'de HashStr>;
+	fn expecting(&self,formatter:&mut core::fmt::Formatter)->core::fmt::Result{
+		write!(formatter,"a sequence of Hash Str")
+	}
+	fn visit_seq<A:SeqAccess<'de>>(mut self,mut seq:A)->Result<Self::Value,A::Error>{
+		let mut table=Vec::with_capacity(seq.size_hint().unwrap_or(0));
+		while let Some(hash_str)=seq.next_element_seed(InternSeed(&mut self.0))?{
+			table.push(hash_str);
+		}
+		Ok(table)
+	}
+}
+
+struct InternSeed<'a,F>(&'a mut F);
+
+impl<'de,F:FnMut(u64,&str)->&'de HashStr> DeserializeSeed<'de> for InternSeed<'_,F>{
+	type Value=&'de HashStr;
+	fn deserialize<D:Deserializer<'de>>(self,deserializer:D)->Result<Self::Value,D::Error>{
+		deserialize_hash_str(deserializer,HashPrefixedVisitor(|hash,str:&str|(self.0)(hash,str)))
+	}
+}
+
+struct DecodeVisitor<T,F>(F,PhantomData<T>);
+
+impl<'de,T:Deserialize<'de>,F:FnMut(u64,&str)->&'de HashStr> Visitor<'de> for DecodeVisitor<T,F>{
+	type Value=T;
+	fn expecting(&self,formatter:&mut core::fmt::Formatter)->core::fmt::Result{
+		write!(formatter,"a string table followed by a value")
+	}
+	fn visit_seq<A:SeqAccess<'de>>(self,mut seq:A)->Result<Self::Value,A::Error>{
+		const EXPECTED:&str="a string table followed by a value";
+		let table=seq.next_element_seed(TableSeed(self.0))?.ok_or_else(||de::Error::invalid_length(0,&EXPECTED))?;
+		let value=Dict{inner:PhantomData::<T>,table:&table};
+		seq.next_element_seed(value)?.ok_or_else(||de::Error::invalid_length(1,&EXPECTED))
+	}
+}
+
+fn decode<'de,T:Deserialize<'de>,D:Deserializer<'de>>(deserializer:D,intern:impl FnMut(u64,&str)->&'de HashStr)->Result<T,D::Error>{
+	deserializer.deserialize_tuple(2,DecodeVisitor(intern,PhantomData))
+}
+
+/// Deserialize a value written with [`DictEncoded`], interning the string table into the global cache.
+#[cfg(feature="global")]
+pub fn decode_global<'de,T:Deserialize<'de>,D:Deserializer<'de>>(deserializer:D)->Result<T,D::Error>{
+	let cache=crate::global::get_cache();
+	decode(deserializer,|hash,str|cache.intern_str_with_hash(hash,str))
+}
+
+/// Deserialize a value written with [`DictEncoded`], interning the string table into the provided cache.
+/// The host must outlive the deserializer input, which dictionary encoded fields borrow for.
+pub fn decode_with<'de,'host:'de,T:Deserialize<'de>,D:Deserializer<'de>>(host:&'host crate::cache::HashStrHost,cache:&mut crate::cache::HashStrCache<'host>,deserializer:D)->Result<T,D::Error>{
+	decode(deserializer,|hash,str|cache.intern_str_with_hash(||host.alloc_str_with_hash(hash,str),hash,str))
+}
+
+// === Dict ===
+// Wraps the deserializer, and every nested deserializer, visitor and access
+// of the value, to carry the string table down to dictionary encoded fields.
+// Fields ask for a newtype struct with the marker name, which the wrapper
+// answers with the bytes of the table entry, borrowed for the input lifetime.
+
+/// Name of the newtype struct requested by dictionary encoded fields.
+const MARKER:&str="$hash_str::serde::dictionary";
+
+/// Accepts the table entry lent by [`Dict`], which is only reachable through `decode_*`.
+struct FieldVisitor<V>(V);
+
+impl<'de,V:Visitor<'de>> Visitor<'de> for FieldVisitor<V>{
+	type Value=V::Value;
+	fn expecting(&self,formatter:&mut core::fmt::Formatter)->core::fmt::Result{
+		write!(formatter,"a dictionary encoded Hash Str")
+	}
+	fn visit_borrowed_bytes<E:de::Error>(self,v:&'de [u8])->Result<Self::Value,E>{
+		self.0.visit_borrowed_bytes(v)
+	}
+	fn visit_newtype_struct<D:Deserializer<'de>>(self,_:D)->Result<Self::Value,D::Error>{
+		Err(de::Error::custom("dictionary encoded HashStr deserialized outside of a dictionary decoder"))
+	}
+}
+
+struct Dict<'t,'de,T>{
+	inner:T,
+	table:&'t [&'de HashStr],
+}
+
+impl<'t,'de,T> Dict<'t,'de,T>{
+	#[inline]
+	fn wrap<U>(&self,inner:U)->Dict<'t,'de,U>{
+		Dict{inner,table:self.table}
+	}
+}
+
+impl<'de,S:DeserializeSeed<'de>> DeserializeSeed<'de> for Dict<'_,'de,S>{
+	type Value=S::Value;
+	#[inline]
+	fn deserialize<D:Deserializer<'de>>(self,deserializer:D)->Result<Self::Value,D::Error>{
+		let deserializer=self.wrap(deserializer);
+		self.inner.deserialize(deserializer)
+	}
+}
+
+macro_rules! dict_deserialize{
+	($($method:ident($($arg:ident:$ty:ty),*),)*)=>{
+		$(
+			#[inline]
+			fn $method<V:Visitor<'de>>(self,$($arg:$ty,)* visitor:V)->Result<V::Value,D::Error>{
+				let visitor=self.wrap(visitor);
+				self.inner.$method($($arg,)* visitor)
+			}
+		)*
+	};
+}
+
+impl<'de,D:Deserializer<'de>> Deserializer<'de> for Dict<'_,'de,D>{
+	type Error=D::Error;
+	dict_deserialize!{
+		deserialize_any(),
+		deserialize_bool(),
+		deserialize_i8(),
+		deserialize_i16(),
+		deserialize_i32(),
+		deserialize_i64(),
+		deserialize_i128(),
+		deserialize_u8(),
+		deserialize_u16(),
+		deserialize_u32(),
+		deserialize_u64(),
+		deserialize_u128(),
+		deserialize_f32(),
+		deserialize_f64(),
+		deserialize_char(),
+		deserialize_str(),
+		deserialize_string(),
+		deserialize_bytes(),
+		deserialize_byte_buf(),
+		deserialize_option(),
+		deserialize_unit(),
+		deserialize_unit_struct(name:&'static str),
+		deserialize_seq(),
+		deserialize_tuple(len:usize),
+		deserialize_tuple_struct(name:&'static str,len:usize),
+		deserialize_map(),
+		deserialize_struct(name:&'static str,fields:&'static [&'static str]),
+		deserialize_enum(name:&'static str,variants:&'static [&'static str]),
+		deserialize_identifier(),
+		deserialize_ignored_any(),
+	}
+	fn deserialize_newtype_struct<V:Visitor<'de>>(self,name:&'static str,visitor:V)->Result<V::Value,D::Error>{
+		if name!=MARKER{
+			let visitor=self.wrap(visitor);
+			return self.inner.deserialize_newtype_struct(name,visitor);
+		}
+		let index=u32::deserialize(self.inner)?;
+		match self.table.get(index as usize){
+			Some(hash_str)=>visitor.visit_borrowed_bytes(hash_str.as_hash_str_bytes()),
+			None=>Err(de::Error::custom("dictionary index out of range")),
+		}
+	}
+	#[inline]
+	fn is_human_readable(&self)->bool{
+		self.inner.is_human_readable()
+	}
+}
+
+macro_rules! dict_visit{
+	($($method:ident($ty:ty),)*)=>{
+		$(
+			#[inline]
+			fn $method<E:de::Error>(self,v:$ty)->Result<Self::Value,E>{
+				self.inner.$method(v)
+			}
+		)*
+	};
+}
+
+impl<'de,V:Visitor<'de>> Visitor<'de> for Dict<'_,'de,V>{
+	type Value=V::Value;
+	fn expecting(&self,formatter:&mut core::fmt::Formatter)->core::fmt::Result{
+		self.inner.expecting(formatter)
+	}
+	dict_visit!{
+		visit_bool(bool),
+		visit_i8(i8),
+		visit_i16(i16),
+		visit_i32(i32),
+		visit_i64(i64),
+		visit_i128(i128),
+		visit_u8(u8),
+		visit_u16(u16),
+		visit_u32(u32),
+		visit_u64(u64),
+		visit_u128(u128),
+		visit_f32(f32),
+		visit_f64(f64),
+		visit_char(char),
+		visit_str(&str),
+		visit_borrowed_str(&'de str),
+		visit_string(String),
+		visit_bytes(&[u8]),
+		visit_borrowed_bytes(&'de [u8]),
+		visit_byte_buf(Vec<u8>),
+	}
+	#[inline]
+	fn visit_none<E:de::Error>(self)->Result<Self::Value,E>{
+		self.inner.visit_none()
+	}
+	#[inline]
+	fn visit_unit<E:de::Error>(self)->Result<Self::Value,E>{
+		self.inner.visit_unit()
+	}
+	#[inline]
+	fn visit_some<D:Deserializer<'de>>(self,deserializer:D)->Result<Self::Value,D::Error>{
+		let deserializer=self.wrap(deserializer);
+		self.inner.visit_some(deserializer)
+	}
+	#[inline]
+	fn visit_newtype_struct<D:Deserializer<'de>>(self,deserializer:D)->Result<Self::Value,D::Error>{
+		let deserializer=self.wrap(deserializer);
+		self.inner.visit_newtype_struct(deserializer)
+	}
+	#[inline]
+	fn visit_seq<A:SeqAccess<'de>>(self,seq:A)->Result<Self::Value,A::Error>{
+		let seq=self.wrap(seq);
+		self.inner.visit_seq(seq)
+	}
+	#[inline]
+	fn visit_map<A:MapAccess<'de>>(self,map:A)->Result<Self::Value,A::Error>{
+		let map=self.wrap(map);
+		self.inner.visit_map(map)
+	}
+	#[inline]
+	fn visit_enum<A:EnumAccess<'de>>(self,data:A)->Result<Self::Value,A::Error>{
+		let data=self.wrap(data);
+		self.inner.visit_enum(data)
+	}
+}
+
+impl<'de,A:SeqAccess<'de>> SeqAccess<'de> for Dict<'_,'de,A>{
+	type Error=A::Error;
+	#[inline]
+	fn next_element_seed<S:DeserializeSeed<'de>>(&mut self,seed:S)->Result<Option<S::Value>,A::Error>{
+		let seed=self.wrap(seed);
+		self.inner.next_element_seed(seed)
+	}
+	#[inline]
+	fn size_hint(&self)->Option<usize>{
+		self.inner.size_hint()
+	}
+}
+
+impl<'de,A:MapAccess<'de>> MapAccess<'de> for Dict<'_,'de,A>{
+	type Error=A::Error;
+	#[inline]
+	fn next_key_seed<S:DeserializeSeed<'de>>(&mut self,seed:S)->Result<Option<S::Value>,A::Error>{
+		let seed=self.wrap(seed);
+		self.inner.next_key_seed(seed)
+	}
+	#[inline]
+	fn next_value_seed<S:DeserializeSeed<'de>>(&mut self,seed:S)->Result<S::Value,A::Error>{
+		let seed=self.wrap(seed);
+		self.inner.next_value_seed(seed)
+	}
+	#[inline]
+	fn size_hint(&self)->Option<usize>{
+		self.inner.size_hint()
+	}
+}
+
+impl<'t,'de,A:EnumAccess<'de>> EnumAccess<'de> for Dict<'t,'de,A>{
+	type Error=A::Error;
+	type Variant=Dict<'t,'de,A::Variant>;
+	#[inline]
+	fn variant_seed<S:DeserializeSeed<'de>>(self,seed:S)->Result<(S::Value,Self::Variant),A::Error>{
+		let seed=self.wrap(seed);
+		let (value,variant)=self.inner.variant_seed(seed)?;
+		Ok((value,Dict{inner:variant,table:self.table}))
+	}
+}
+
+impl<'de,A:VariantAccess<'de>> VariantAccess<'de> for Dict<'_,'de,A>{
+	type Error=A::Error;
+	#[inline]
+	fn unit_variant(self)->Result<(),A::Error>{
+		self.inner.unit_variant()
+	}
+	#[inline]
+	fn newtype_variant_seed<S:DeserializeSeed<'de>>(self,seed:S)->Result<S::Value,A::Error>{
+		let seed=self.wrap(seed);
+		self.inner.newtype_variant_seed(seed)
+	}
+	#[inline]
+	fn tuple_variant<V:Visitor<'de>>(self,len:usize,visitor:V)->Result<V::Value,A::Error>{
+		let visitor=self.wrap(visitor);
+		self.inner.tuple_variant(len,visitor)
+	}
+	#[inline]
+	fn struct_variant<V:Visitor<'de>>(self,fields:&'static [&'static str],visitor:V)->Result<V::Value,A::Error>{
+		let visitor=self.wrap(visitor);
+		self.inner.struct_variant(fields,visitor)
+	}
+}
+
+// === Collect ===
+// A serializer which discards everything, used to visit every
+// dictionary encoded HashStr before the string table is written.
+
+#[derive(Debug)]
+struct CollectError(String);
+impl core::fmt::Display for CollectError{
+	fn fmt(&self,f:&mut core::fmt::Formatter<'_>)->core::fmt::Result{
+		f.write_str(&self.0)
+	}
+}
+impl std::error::Error for CollectError{}
+impl ser::Error for CollectError{
+	fn custom<T:core::fmt::Display>(msg:T)->Self{
+		CollectError(msg.to_string())
+	}
+}
+
+struct Collect;
+
+macro_rules! collect_primitives{
+	($($method:ident($ty:ty),)*)=>{
+		$(
+			#[inline]
+			fn $method(self,_:$ty)->Result<(),CollectError>{
+				Ok(())
+			}
+		)*
+	};
+}
+
+impl Serializer for Collect{
+	type Ok=();
+	type Error=CollectError;
+	type SerializeSeq=Self;
+	type SerializeTuple=Self;
+	type SerializeTupleStruct=Self;
+	type SerializeTupleVariant=Self;
+	type SerializeMap=Self;
+	type SerializeStruct=Self;
+	type SerializeStructVariant=Self;
+	collect_primitives!{
+		serialize_bool(bool),
+		serialize_i8(i8),
+		serialize_i16(i16),
+		serialize_i32(i32),
+		serialize_i64(i64),
+		serialize_i128(i128),
+		serialize_u8(u8),
+		serialize_u16(u16),
+		serialize_u32(u32),
+		serialize_u64(u64),
+		serialize_u128(u128),
+		serialize_f32(f32),
+		serialize_f64(f64),
+		serialize_char(char),
+		serialize_str(&str),
+		serialize_bytes(&[u8]),
+		serialize_unit_struct(&'static str),
+	}
+	fn serialize_none(self)->Result<(),CollectError>{
+		Ok(())
+	}
+	fn serialize_some<T:Serialize+?Sized>(self,value:&T)->Result<(),CollectError>{
+		value.serialize(self)
+	}
+	fn serialize_unit(self)->Result<(),CollectError>{
+		Ok(())
+	}
+	fn serialize_unit_variant(self,_:&'static str,_:u32,_:&'static str)->Result<(),CollectError>{
+		Ok(())
+	}
+	fn serialize_newtype_struct<T:Serialize+?Sized>(self,_:&'static str,value:&T)->Result<(),CollectError>{
+		value.serialize(self)
+	}
+	fn serialize_newtype_variant<T:Serialize+?Sized>(self,_:&'static str,_:u32,_:&'static str,value:&T)->Result<(),CollectError>{
+		value.serialize(self)
+	}
+	fn serialize_seq(self,_:Option<usize>)->Result<Self,CollectError>{
+		Ok(self)
+	}
+	fn serialize_tuple(self,_:usize)->Result<Self,CollectError>{
+		Ok(self)
+	}
+	fn serialize_tuple_struct(self,_:&'static str,_:usize)->Result<Self,CollectError>{
+		Ok(self)
+	}
+	fn serialize_tuple_variant(self,_:&'static str,_:u32,_:&'static str,_:usize)->Result<Self,CollectError>{
+		Ok(self)
+	}
+	fn serialize_map(self,_:Option<usize>)->Result<Self,CollectError>{
+		Ok(self)
+	}
+	fn serialize_struct(self,_:&'static str,_:usize)->Result<Self,CollectError>{
+		Ok(self)
+	}
+	fn serialize_struct_variant(self,_:&'static str,_:u32,_:&'static str,_:usize)->Result<Self,CollectError>{
+		Ok(self)
+	}
+}
+
+macro_rules! collect_compound{
+	($($trait:ident::$method:ident,)*)=>{
+		$(
+			impl ser::$trait for Collect{
+				type Ok=();
+				type Error=CollectError;
+				#[inline]
+				fn $method<T:Serialize+?Sized>(&mut self,value:&T)->Result<(),CollectError>{
+					value.serialize(Collect)
+				}
+				#[inline]
+				fn end(self)->Result<(),CollectError>{
+					Ok(())
+				}
+			}
+		)*
+	};
+}
+collect_compound!{
+	SerializeSeq::serialize_element,
+	SerializeTuple::serialize_element,
+	SerializeTupleStruct::serialize_field,
+	SerializeTupleVariant::serialize_field,
+}
+
+impl ser::SerializeMap for Collect{
+	type Ok=();
+	type Error=CollectError;
+	fn serialize_key<T:Serialize+?Sized>(&mut self,key:&T)->Result<(),CollectError>{
+		key.serialize(Collect)
+	}
+	fn serialize_value<T:Serialize+?Sized>(&mut self,value:&T)->Result<(),CollectError>{
+		value.serialize(Collect)
+	}
+	fn end(self)->Result<(),CollectError>{
+		Ok(())
+	}
+}
+impl ser::SerializeStruct for Collect{
+	type Ok=();
+	type Error=CollectError;
+	fn serialize_field<T:Serialize+?Sized>(&mut self,_:&'static str,value:&T)->Result<(),CollectError>{
+		value.serialize(Collect)
+	}
+	fn end(self)->Result<(),CollectError>{
+		Ok(())
+	}
+}
+impl ser::SerializeStructVariant for Collect{
+	type Ok=();
+	type Error=CollectError;
+	fn serialize_field<T:Serialize+?Sized>(&mut self,_:&'static str,value:&T)->Result<(),CollectError>{
+		value.serialize(Collect)
+	}
+	fn end(self)->Result<(),CollectError>{
+		Ok(())
+	}
+}
+
+#[cfg(feature="global")]
+#[test]
+fn test_dictionary(){
+	#[derive(Debug,PartialEq,Serialize,Deserialize)]
+	enum Kind<'a>{
+		Plain,
+		Named(#[serde(borrow,with="self")] &'a HashStr),
+	}
+	#[derive(Debug,PartialEq,Serialize,Deserialize)]
+	struct Record<'a>{
+		#[serde(borrow,with="self")]
+		key:&'a HashStr,
+		#[serde(borrow)]
+		kind:Option<Kind<'a>>,
+		value:u32,
+	}
+	let keys=[crate::hstr!("dictionary_alpha"),crate::hstr!("dictionary_beta"),crate::hstr!("dictionary_gamma")];
+	let records:Vec<Record>=(0..300).map(|i|Record{
+		key:keys[i%3],
+		kind:match i%4{0=>None,1=>Some(Kind::Plain),_=>Some(Kind::Named(keys[(i/3)%3]))},
+		value:i as u32,
+	}).collect();
+
+	let encoded=postcard::to_allocvec(&DictEncoded(&records)).unwrap();
+	let plain:usize=records.iter().map(|record|record.key.as_hash_str_bytes().len()*2).sum();
+	assert!(encoded.len()<plain/4);
+
+	let decoded:Vec<Record>=decode_global(&mut postcard::Deserializer::from_bytes(&encoded)).unwrap();
+	assert_eq!(decoded,records);
+
+	let host=crate::cache::HashStrHost::new();
+	let mut cache=crate::cache::HashStrCache::new();
+	let decoded:Vec<Record>=decode_with(&host,&mut cache,&mut postcard::Deserializer::from_bytes(&encoded)).unwrap();
+	assert_eq!(decoded,records);
+	assert_eq!(cache.len(),3);
+	assert!(core::ptr::addr_eq(decoded[3].key,cache.get("dictionary_alpha").unwrap()));
+
+	// human readable formats write the table as sequences of bytes
+	let json=serde_json::to_string(&DictEncoded(&records)).unwrap();
+	let mut cache=crate::cache::HashStrCache::new();
+	let decoded:Vec<Record>=decode_with(&host,&mut cache,&mut serde_json::Deserializer::from_str(&json)).unwrap();
+	assert_eq!(decoded,records);
+	// strings are also accepted in the table, calculating the hash
+	let decoded:Vec<Record>=decode_global(&mut serde_json::Deserializer::from_str(r#"[["dictionary_alpha"],[{"key":0,"kind":null,"value":7}]]"#)).unwrap();
+	assert!(core::ptr::addr_eq(decoded[0].key,crate::global::get_cache().intern("dictionary_alpha")));
+
+	#[derive(Debug,PartialEq,Serialize,Deserialize)]
+	struct Static{
+		#[serde(with="global")]
+		key:&'static HashStr,
+	}
+	let statics:Vec<Static>=keys.iter().map(|&key|Static{key}).collect();
+	let encoded=postcard::to_allocvec(&DictEncoded(&statics)).unwrap();
+	let decoded:Vec<Static>=decode_with(&host,&mut cache,&mut postcard::Deserializer::from_bytes(&encoded)).unwrap();
+	assert!(core::ptr::addr_eq(decoded[1].key,crate::global::get_cache().intern("dictionary_beta")));
+
+	// dictionary fields are an error outside of DictEncoded and the decoders
+	assert!(postcard::to_allocvec(&records[0]).is_err());
+	assert!(serde_json::from_str::<Record>(r#"{"key":0,"kind":null,"value":7}"#).is_err());
+	assert!(decode_global::<Vec<Record>,_>(&mut serde_json::Deserializer::from_str(r#"[[],[{"key":0,"kind":null,"value":7}]]"#)).is_err());
+}
@@ -1,32 +1,257 @@
-use serde::de::{Error,Visitor};
-use crate::global::get_cache;
-use crate::ornaments::GetHash;
+//! Visitors and field adapters which intern into the global cache.
+//!
+//! As a `#[serde(with="hash_str::serde::global")]` module, HashStrs are
+//! serialized as plain strings and deserialized into `&'static HashStr`
+//! interned in the global cache.  Also supports `Option`, `Vec`,
+//! sets and map keys, including `HashStrMap` and `HashStrSet`.
+//!
+//! ```rust
+//! use hash_str::HashStr;
+//! use serde::{Serialize,Deserialize};
+//!
+//! #[derive(Serialize,Deserialize)]
+//! struct Event{
+//!     #[serde(with="hash_str::serde::global")]
+//!     kind:&'static HashStr,
+//!     #[serde(with="hash_str::serde::global")]
+//!     counts:std::collections::BTreeMap<&'static HashStr,u32>,
+//! }
+//!
+//! let event:Event=serde_json::from_str(r#"{"kind":"click","counts":{"a":1}}"#).unwrap();
+//! assert!(core::ptr::addr_eq(event.kind,hash_str::get_cache().intern("click")));
+//! ```
+use serde::{Serialize,Serializer,Deserializer};
+use serde::ser::SerializeTuple;
+use serde::de::{DeserializeSeed,Error,SeqAccess,Visitor};
+use crate::global::{get_cache,Bins};
+use crate::hash::HashTarget;
 use crate::hash_str::HashStr;
-use super::HashStrVisitorZeroCopy;
+use super::adapter::{collect_bytes,Global,SerializeAdapter,DeserializeAdapter};
+use super::hash_str::{deserialize_hash_str,split_hash_str};
+
+pub fn serialize<T:SerializeAdapter<Global>+?Sized,S:Serializer>(value:&T,serializer:S)->Result<S::Ok,S::Error>{
+	value.serialize_with(serializer)
+}
+
+pub fn deserialize<'de,T:DeserializeAdapter<'de,Global>,D:Deserializer<'de>>(deserializer:D)->Result<T,D::Error>{
+	T::deserialize_with(deserializer)
+}
 
 /// Read hash value and str and intern into global cache.
+/// Strings are also accepted, calculating the hash on the fly.
+/// The hash is trusted as is, see [`intern`] for the cross-target restriction.
 pub struct HashStrVisitorGlobalFromHashStr;
 
-impl Visitor<'_> for HashStrVisitorGlobalFromHashStr{
+impl<'de> Visitor<'de> for HashStrVisitorGlobalFromHashStr{
 	type Value=&'static HashStr;
-	fn expecting(&self,formatter:&mut std::fmt::Formatter)->std::fmt::Result{
+	fn expecting(&self,formatter:&mut core::fmt::Formatter)->core::fmt::Result{
 		write!(formatter,"Hash Str")
 	}
-	fn visit_borrowed_bytes<E:Error>(self,v:&[u8])->Result<Self::Value,E>{
-		let h=HashStrVisitorZeroCopy.visit_bytes(v)?;
-		Ok(get_cache().intern_str_with_hash(h.get_hash(),h.as_str()))
+	fn visit_bytes<E:Error>(self,v:&[u8])->Result<Self::Value,E>{
+		let (hash,str)=split_hash_str(v)?;
+		Ok(get_cache().intern_str_with_hash(hash,str))
+	}
+	fn visit_str<E:Error>(self,v:&str)->Result<Self::Value,E>{
+		HashStrVisitorGlobalFromStr.visit_str(v)
+	}
+	fn visit_seq<A:SeqAccess<'de>>(self,seq:A)->Result<Self::Value,A::Error>{
+		self.visit_bytes(&collect_bytes(seq)?)
 	}
 }
 
+/// Deserialize a HashStr interned into the global cache.  Accepts hash prefixed
+/// bytes, borrowed or owned, and strings, so it works with formats which cannot
+/// borrow from the input.  Usable as `#[serde(deserialize_with="hash_str::serde::global::intern")]`.
+///
+/// The hash in hash prefixed bytes is trusted as is, and the payload does not
+/// record which target wrote it.  Bytes from a target with a different endianness
+/// or `HashTarget` would intern strings under the wrong hash for the whole process,
+/// so only read them from the same target, or use the `as_str` encoding.
+/// `BinsSnapshot` records the target and rehashes foreign snapshots instead.
+///
+/// ```rust
+/// let hs=hash_str::serde::global::intern(&mut serde_json::Deserializer::from_reader(&b"\"bruh\""[..])).unwrap();
+/// assert!(core::ptr::addr_eq(hs,hash_str::get_cache().intern("bruh")));
+/// ```
+pub fn intern<'de,D:Deserializer<'de>>(deserializer:D)->Result<&'static HashStr,D::Error>{
+	deserialize_hash_str(deserializer,HashStrVisitorGlobalFromHashStr)
+}
+
 /// Read str and intern into global cache, calculates hash on the fly.
 pub struct HashStrVisitorGlobalFromStr;
 
 impl Visitor<'_> for HashStrVisitorGlobalFromStr{
 	type Value=&'static HashStr;
-	fn expecting(&self,formatter:&mut std::fmt::Formatter)->std::fmt::Result{
+	fn expecting(&self,formatter:&mut core::fmt::Formatter)->core::fmt::Result{
 		write!(formatter,"Hash Str")
 	}
 	fn visit_str<E:Error>(self,v:&str)->Result<Self::Value,E>{
 		Ok(get_cache().intern(v))
 	}
 }
+
+/// A serializable snapshot of the HashStrs in the global cache, including
+/// their hashes.  Restore it with `Bins::preload`.  The snapshot records the
+/// hash and endianness of the target, and the strings are hashed again when
+/// preloading on a target where they differ.
+///
+/// ```rust
+/// use hash_str::get_cache;
+///
+/// get_cache().intern("bruh");
+/// let bytes=postcard::to_allocvec(&get_cache().snapshot()).unwrap();
+///
+/// // at startup of the next run
+/// get_cache().preload(&mut postcard::Deserializer::from_bytes(&bytes)).unwrap();
+/// ```
+#[derive(Debug,Clone)]
+pub struct BinsSnapshot<'host>{
+	hash_strs:Vec<&'host HashStr>,
+}
+
+impl<'host> BinsSnapshot<'host>{
+	#[inline]
+	pub fn iter(&self)->impl Iterator<Item=&'host HashStr>+'_{
+		self.hash_strs.iter().copied()
+	}
+	#[inline]
+	pub fn len(&self)->usize{
+		self.hash_strs.len()
+	}
+	#[inline]
+	pub fn is_empty(&self)->bool{
+		self.hash_strs.is_empty()
+	}
+}
+
+/// Serialized as a tuple of the hash id, whether the hashes are big endian,
+/// and the hash prefixed strings.
+impl Serialize for BinsSnapshot<'_>{
+	fn serialize<S:Serializer>(&self,serializer:S)->Result<S::Ok,S::Error>{
+		let mut tuple=serializer.serialize_tuple(3)?;
+		tuple.serialize_element(&HashTarget::NATIVE.hash_id())?;
+		tuple.serialize_element(&HashTarget::NATIVE.big_endian)?;
+		tuple.serialize_element(&self.hash_strs)?;
+		tuple.end()
+	}
+}
+
+/// Intern each HashStr of a snapshot, reusing the saved hash
+/// unless the snapshot was taken on a different target.
+#[derive(Clone,Copy)]
+struct PreloadSeed<'a,'host>{
+	bins:&'a Bins<'host>,
+	rehash:bool,
+}
+
+impl<'de> DeserializeSeed<'de> for PreloadSeed<'_,'_>{
+	type Value=();
+	fn deserialize<D:Deserializer<'de>>(self,deserializer:D)->Result<Self::Value,D::Error>{
+		deserialize_hash_str(deserializer,self)
+	}
+}
+
+impl<'de> Visitor<'de> for PreloadSeed<'_,'_>{
+	type Value=();
+	fn expecting(&self,formatter:&mut core::fmt::Formatter)->core::fmt::Result{
+		write!(formatter,"Hash Str")
+	}
+	fn visit_bytes<E:Error>(self,v:&[u8])->Result<Self::Value,E>{
+		let (hash,str)=split_hash_str(v)?;
+		if self.rehash{
+			self.bins.intern(str);
+		}else{
+			self.bins.intern_str_with_hash(hash,str);
+		}
+		Ok(())
+	}
+	fn visit_seq<A:SeqAccess<'de>>(self,seq:A)->Result<Self::Value,A::Error>{
+		self.visit_bytes(&collect_bytes(seq)?)
+	}
+}
+
+/// The sequence of HashStrs in a snapshot.
+struct PreloadHashStrs<'a,'host>(PreloadSeed<'a,'host>);
+
+impl<'de> DeserializeSeed<'de> for PreloadHashStrs<'_,'_>{
+	type Value=usize;
+	fn deserialize<D:Deserializer<'de>>(self,deserializer:D)->Result<Self::Value,D::Error>{
+		deserializer.deserialize_seq(self)
+	}
+}
+
+impl<'de> Visitor<'de> for PreloadHashStrs<'_,'_>{
+	type Value=usize;
+	fn expecting(&self,formatter:&mut core::fmt::Formatter)->core::fmt::Result{
+		write!(formatter,"a sequence of Hash Str")
+	}
+	fn visit_seq<A:SeqAccess<'de>>(self,mut seq:A)->Result<Self::Value,A::Error>{
+		let mut count=0;
+		while seq.next_element_seed(self.0)?.is_some(){
+			count+=1;
+		}
+		Ok(count)
+	}
+}
+
+struct PreloadVisitor<'a,'host>(&'a Bins<'host>);
+
+impl<'de> Visitor<'de> for PreloadVisitor<'_,'_>{
+	type Value=usize;
+	fn expecting(&self,formatter:&mut core::fmt::Formatter)->core::fmt::Result{
+		write!(formatter,"a Bins snapshot")
+	}
+	fn visit_seq<A:SeqAccess<'de>>(self,mut seq:A)->Result<Self::Value,A::Error>{
+		let hash_id:u32=seq.next_element()?.ok_or_else(||Error::invalid_length(0,&self))?;
+		let big_endian:bool=seq.next_element()?.ok_or_else(||Error::invalid_length(1,&self))?;
+		let rehash=hash_id!=HashTarget::NATIVE.hash_id()||big_endian!=HashTarget::NATIVE.big_endian;
+		let seed=PreloadHashStrs(PreloadSeed{bins:self.0,rehash});
+		seq.next_element_seed(seed)?.ok_or_else(||Error::invalid_length(2,&self))
+	}
+}
+
+impl<'host> Bins<'host>{
+	/// Take a serializable snapshot of the HashStrs in the global cache.
+	#[inline]
+	pub fn snapshot(&self)->BinsSnapshot<'host>{
+		BinsSnapshot{hash_strs:self.iter().collect()}
+	}
+	/// Intern a serialized `BinsSnapshot` into the global cache without
+	/// hashing the strings again, unless the snapshot was taken on a target
+	/// with a different hash or endianness.  Returns the number of HashStrs in the snapshot.
+	pub fn preload<'de,D:Deserializer<'de>>(&self,deserializer:D)->Result<usize,D::Error>{
+		deserializer.deserialize_tuple(3,PreloadVisitor(self))
+	}
+}
+
+#[test]
+fn test_snapshot(){
+	use crate::hash::make_hash;
+	let cache=get_cache();
+	let hs=cache.intern("serde_snapshot");
+	let snapshot=cache.snapshot();
+	assert!(snapshot.iter().any(|s|core::ptr::addr_eq(s,hs)));
+	let bytes=postcard::to_allocvec(&snapshot).unwrap();
+	let count=cache.preload(&mut postcard::Deserializer::from_bytes(&bytes)).unwrap();
+	assert_eq!(count,snapshot.len());
+	// preloading existing strings does not allocate new ones
+	assert!(core::ptr::addr_eq(cache.get("serde_snapshot").unwrap(),hs));
+
+	// a snapshot from another process
+	let other=BinsSnapshot{hash_strs:vec![&*Box::leak(HashStr::anonymous("serde_preload".to_owned()))]};
+	let bytes=postcard::to_allocvec(&other).unwrap();
+	assert_eq!(cache.preload(&mut postcard::Deserializer::from_bytes(&bytes)).unwrap(),1);
+	assert_eq!(cache.get("serde_preload").unwrap().as_str(),"serde_preload");
+
+	// human readable formats
+	let json=serde_json::to_string(&other).unwrap();
+	assert_eq!(cache.preload(&mut serde_json::Deserializer::from_str(&json)).unwrap(),1);
+
+	// a snapshot from a target with a different hash is hashed again
+	let mut hash_str_bytes=make_hash("serde_foreign").wrapping_add(1).to_ne_bytes().to_vec();
+	hash_str_bytes.extend_from_slice(b"serde_foreign");
+	let other_id=if HashTarget::NATIVE.hash_id()==1{2}else{1};
+	let bytes=postcard::to_allocvec(&(other_id,HashTarget::NATIVE.big_endian,vec![hash_str_bytes])).unwrap();
+	assert_eq!(cache.preload(&mut postcard::Deserializer::from_bytes(&bytes)).unwrap(),1);
+	assert_eq!(cache.get("serde_foreign").unwrap().precomputed_hash(),make_hash("serde_foreign"));
+}
@@ -0,0 +1,34 @@
+//! Serialize HashStrs as bytes prefixed with the precomputed hash, reusing
+//! the hash when deserializing.  Deserializes into `Box<HashStr>`, or interns
+//! `&'static HashStr` into the global cache.  The hash is only valid on
+//! targets with the same endianness and hash.
+//! Also supports `Option`, `Vec`,
+//! sets and map keys of these types, including `HashStrMap` and `HashStrSet`.
+//!
+//! ```rust
+//! use hash_str::HashStr;
+//! use serde::{Serialize,Deserialize};
+//!
+//! #[derive(Serialize,Deserialize)]
+//! struct Record{
+//!     #[serde(with="hash_str::serde::hash_prefixed")]
+//!     key:Box<HashStr>,
+//!     #[serde(with="hash_str::serde::hash_prefixed")]
+//!     alias:Option<Box<HashStr>>,
+//! }
+//!
+//! let record=Record{key:HashStr::anonymous("bruh".to_owned()),alias:None};
+//! let bytes=postcard::to_allocvec(&record).unwrap();
+//! let record:Record=postcard::from_bytes(&bytes).unwrap();
+//! assert_eq!(record.key.as_str(),"bruh");
+//! ```
+use serde::{Serializer,Deserializer};
+use super::adapter::{HashPrefixed,SerializeAdapter,DeserializeAdapter};
+
+pub fn serialize<T:SerializeAdapter<HashPrefixed>+?Sized,S:Serializer>(value:&T,serializer:S)->Result<S::Ok,S::Error>{
+	value.serialize_with(serializer)
+}
+
+pub fn deserialize<'de,T:DeserializeAdapter<'de,HashPrefixed>,D:Deserializer<'de>>(deserializer:D)->Result<T,D::Error>{
+	T::deserialize_with(deserializer)
+}
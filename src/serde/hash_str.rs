@@ -1,6 +1,8 @@
 use crate::hash_str::{HashStr,RefFromBytesError};
 use serde::{Serialize,Serializer,Deserialize,Deserializer};
 use serde::de::{Error,Unexpected,Visitor};
+use alloc::boxed::Box;
+use super::adapter::HashPrefixedVisitor;
 
 impl Serialize for HashStr{
 	fn serialize<S:Serializer>(&self,serializer:S)->Result<S::Ok,S::Error>{
@@ -8,12 +10,24 @@ impl Serialize for HashStr{
 	}
 }
 
+/// Split hash prefixed bytes into the hash and the str.  Unlike `HashStr::ref_from_bytes`
+/// this does not require the bytes to be aligned, so it works with transient bytes.
+pub(crate) fn split_hash_str<E:Error>(v:&[u8])->Result<(u64,&str),E>{
+	let Some((hash,str))=v.split_first_chunk::<{crate::hash_str::SIZE_HASH}>()else{
+		return Err(E::invalid_length(v.len(),&"8 or longer"));
+	};
+	match core::str::from_utf8(str){
+		Ok(str)=>Ok((u64::from_ne_bytes(*hash),str)),
+		Err(_)=>Err(E::invalid_value(Unexpected::Bytes(v),&"valid utf8 after position 8")),
+	}
+}
+
 pub struct HashStrVisitorZeroCopy;
 
 impl<'de> Visitor<'de> for HashStrVisitorZeroCopy{
 	type Value=&'de HashStr;
 
-	fn expecting(&self,formatter:&mut std::fmt::Formatter)->std::fmt::Result{
+	fn expecting(&self,formatter:&mut core::fmt::Formatter)->core::fmt::Result{
 		write!(formatter,"Hash Str")
 	}
 
@@ -31,3 +45,91 @@ impl<'a,'de:'a> Deserialize<'de> for &'a HashStr{
 		deserializer.deserialize_bytes(HashStrVisitorZeroCopy)
 	}
 }
+
+/// Hint hash prefixed bytes, except for human readable formats such as JSON
+/// which may report strings as bytes.  They are asked for any type instead.
+pub(crate) fn deserialize_hash_str<'de,D:Deserializer<'de>,V:Visitor<'de>>(deserializer:D,visitor:V)->Result<V::Value,D::Error>{
+	if deserializer.is_human_readable(){
+		deserializer.deserialize_any(visitor)
+	}else{
+		deserializer.deserialize_bytes(visitor)
+	}
+}
+
+/// Deserialize an owned HashStr from hash prefixed bytes, borrowed or owned,
+/// reusing the hash.  Strings are also accepted, calculating the hash.
+///
+/// The hash is trusted as is, and the payload does not record which target
+/// wrote it, so only read hash prefixed bytes written by a target with the same
+/// endianness and `HashTarget`.  Use the `as_str` encoding to cross targets.
+impl<'de> Deserialize<'de> for Box<HashStr>{
+	fn deserialize<D:Deserializer<'de>>(deserializer:D)->Result<Self,D::Error>{
+		deserialize_hash_str(deserializer,HashPrefixedVisitor(HashStr::boxed_str_with_hash))
+	}
+}
+
+#[cfg(feature="std")]
+impl<V:Serialize> Serialize for crate::collections::HashStrMap<'_,V>{
+	#[inline]
+	fn serialize<S:Serializer>(&self,serializer:S)->Result<S::Ok,S::Error>{
+		(**self).serialize(serializer)
+	}
+}
+#[cfg(feature="std")]
+impl<'a,'de:'a,V:Deserialize<'de>> Deserialize<'de> for crate::collections::HashStrMap<'a,V>{
+	#[inline]
+	fn deserialize<D:Deserializer<'de>>(deserializer:D)->Result<Self,D::Error>{
+		Ok(Self::from_inner(Deserialize::deserialize(deserializer)?))
+	}
+}
+#[cfg(feature="std")]
+impl Serialize for crate::collections::HashStrSet<'_>{
+	#[inline]
+	fn serialize<S:Serializer>(&self,serializer:S)->Result<S::Ok,S::Error>{
+		(**self).serialize(serializer)
+	}
+}
+#[cfg(feature="std")]
+impl<'a,'de:'a> Deserialize<'de> for crate::collections::HashStrSet<'a>{
+	#[inline]
+	fn deserialize<D:Deserializer<'de>>(deserializer:D)->Result<Self,D::Error>{
+		Ok(Self::from_inner(Deserialize::deserialize(deserializer)?))
+	}
+}
+
+#[test]
+fn test_deserialize_owned(){
+	let hs=HashStr::anonymous("bruh".to_owned());
+	// readers cannot lend out borrowed bytes
+	let json=serde_json::to_vec(&*hs).unwrap();
+	let owned:Box<HashStr>=serde_json::from_reader(json.as_slice()).unwrap();
+	assert_eq!(owned,hs);
+	assert_eq!(owned.precomputed_hash(),hs.precomputed_hash());
+	let owned:Box<HashStr>=serde_json::from_reader(&b"\"bruh\""[..]).unwrap();
+	assert_eq!(owned.precomputed_hash(),hs.precomputed_hash());
+	let bytes=postcard::to_allocvec(&*hs).unwrap();
+	let owned:Box<HashStr>=postcard::from_bytes(&bytes).unwrap();
+	assert_eq!(owned,hs);
+	assert!(serde_json::from_reader::<_,Box<HashStr>>(&b"[1,2]"[..]).is_err());
+	// the hash is taken from the payload, not recomputed
+	let mut foreign=bytes.clone();
+	foreign[1]^=1;
+	let owned:Box<HashStr>=postcard::from_bytes(&foreign).unwrap();
+	assert_eq!(owned.as_str(),"bruh");
+	assert_ne!(owned.precomputed_hash(),hs.precomputed_hash());
+
+	#[cfg(feature="std")]
+	{
+		let map:crate::collections::HashStrMap<u32>=[(&*hs,1)].into_iter().collect();
+		// serialized the same as the standard HashMap
+		assert_eq!(postcard::to_allocvec(&map).unwrap(),postcard::to_allocvec(&*map).unwrap());
+	}
+
+	#[cfg(feature="global")]
+	{
+		let interned=super::global::intern(&mut serde_json::Deserializer::from_reader(json.as_slice())).unwrap();
+		assert!(core::ptr::addr_eq(interned,crate::global::get_cache().intern("bruh")));
+		let interned=super::global::intern(&mut postcard::Deserializer::from_bytes(&bytes)).unwrap();
+		assert!(core::ptr::addr_eq(interned,crate::global::get_cache().intern("bruh")));
+	}
+}
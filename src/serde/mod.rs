@@ -1,12 +1,20 @@
 mod hash_str;
 pub use hash_str::*;
 
+mod adapter;
+pub use adapter::{SerializeAdapter,DeserializeAdapter};
+pub mod as_str;
+pub mod hash_prefixed;
+
 #[cfg(feature="cache")]
 mod cache;
 #[cfg(feature="cache")]
 pub use cache::*;
 
 #[cfg(feature="global")]
-mod global;
+pub mod global;
 #[cfg(feature="global")]
-pub use global::*;
+pub use global::{HashStrVisitorGlobalFromHashStr,HashStrVisitorGlobalFromStr,BinsSnapshot};
+
+#[cfg(all(feature="std",feature="cache"))]
+pub mod dictionary;
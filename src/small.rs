@@ -0,0 +1,226 @@
+use crate::ornaments::{GetHash,HashedStr};
+use crate::hash_str::HashStr;
+#[cfg(feature="std")]
+use crate::hash::IdentityHasher;
+#[cfg(feature="std")]
+use std::collections::{HashMap,HashSet};
+#[cfg(feature="std")]
+use core::hash::BuildHasherDefault;
+use core::hash::{Hash,Hasher};
+
+/// Strings up to this many bytes are stored inline in a SmallHashStr.
+pub const INLINE_CAPACITY:usize=22;
+
+/// A Copy string with a precomputed hash.  Short strings are stored inline
+/// with their hash, requiring no allocation and no host.  Longer strings
+/// fall back to a pointer to an interned HashStr.
+#[derive(Clone,Copy)]
+pub struct SmallHashStr<'a>(Repr<'a>);
+
+#[derive(Clone,Copy)]
+enum Repr<'a>{
+	Inline{
+		hash:u64,
+		len:u8,
+		bytes:[u8;INLINE_CAPACITY],
+	},
+	Interned(&'a HashStr),
+}
+
+impl<'a> SmallHashStr<'a>{
+	/// Store the string inline if it is short enough.
+	#[inline]
+	pub fn try_inline(index:impl GetHash+AsRef<str>)->Option<Self>{
+		let str=index.as_ref();
+		if INLINE_CAPACITY<str.len(){
+			return None;
+		}
+		Some(Self::inline_str_with_hash(index.get_hash(),str))
+	}
+	/// Store the string inline if it is short enough,
+	/// otherwise call `with` to get an interned HashStr.
+	#[inline]
+	pub fn new_with<'b>(index:impl GetHash+Into<&'b str>,with:impl FnOnce(HashedStr<'b>)->&'a HashStr)->Self{
+		let (hash,str)=(index.get_hash(),index.into());
+		if str.len()<=INLINE_CAPACITY{
+			Self::inline_str_with_hash(hash,str)
+		}else{
+			SmallHashStr(Repr::Interned(with(HashedStr{hash,str})))
+		}
+	}
+	#[inline]
+	fn inline_str_with_hash(hash:u64,str:&str)->Self{
+		let mut bytes=[0;INLINE_CAPACITY];
+		bytes[..str.len()].copy_from_slice(str.as_bytes());
+		SmallHashStr(Repr::Inline{
+			hash,
+			len:str.len() as u8,
+			bytes,
+		})
+	}
+	#[inline]
+	pub const fn precomputed_hash(&self)->u64{
+		match &self.0{
+			Repr::Inline{hash,..}=>*hash,
+			Repr::Interned(hash_str)=>hash_str.precomputed_hash(),
+		}
+	}
+	#[inline]
+	pub fn as_str(&self)->&str{
+		match &self.0{
+			// SAFETY: inline bytes are always copied from a valid str
+			Repr::Inline{len,bytes,..}=>unsafe{core::str::from_utf8_unchecked(&bytes[..*len as usize])},
+			Repr::Interned(hash_str)=>hash_str.as_str(),
+		}
+	}
+	/// Whether the string is stored inline.
+	#[inline]
+	pub const fn is_inline(&self)->bool{
+		matches!(self.0,Repr::Inline{..})
+	}
+	/// The interned HashStr if the string is not stored inline.
+	#[inline]
+	pub const fn as_interned(&self)->Option<&'a HashStr>{
+		match self.0{
+			Repr::Inline{..}=>None,
+			Repr::Interned(hash_str)=>Some(hash_str),
+		}
+	}
+}
+
+impl<'a> From<&'a HashStr> for SmallHashStr<'a>{
+	#[inline]
+	fn from(value:&'a HashStr)->Self{
+		// short strings are always inline so that equal strings have equal representations
+		match Self::try_inline(value){
+			Some(small)=>small,
+			None=>SmallHashStr(Repr::Interned(value)),
+		}
+	}
+}
+
+impl core::ops::Deref for SmallHashStr<'_>{
+	type Target=str;
+	#[inline]
+	fn deref(&self)->&Self::Target{
+		self.as_str()
+	}
+}
+impl AsRef<str> for SmallHashStr<'_>{
+	#[inline]
+	fn as_ref(&self)->&str{
+		self.as_str()
+	}
+}
+impl core::fmt::Debug for SmallHashStr<'_>{
+	#[inline]
+	fn fmt(&self,f:&mut core::fmt::Formatter<'_>)->core::fmt::Result{
+		f.debug_struct("SmallHashStr")
+			.field("hash",&self.precomputed_hash())
+			.field("str",&self.as_str())
+			.finish()
+	}
+}
+impl core::fmt::Display for SmallHashStr<'_>{
+	#[inline]
+	fn fmt(&self,f:&mut core::fmt::Formatter<'_>)->core::fmt::Result{
+		f.write_str(self.as_str())
+	}
+}
+impl PartialEq for SmallHashStr<'_>{
+	#[inline]
+	fn eq(&self,other:&Self)->bool{
+		self.precomputed_hash()==other.precomputed_hash()&&self.as_str().eq(other.as_str())
+	}
+}
+impl Eq for SmallHashStr<'_>{}
+impl PartialOrd for SmallHashStr<'_>{
+	#[inline]
+	fn partial_cmp(&self,other:&Self)->Option<core::cmp::Ordering>{
+		Some(self.cmp(other))
+	}
+}
+impl Ord for SmallHashStr<'_>{
+	#[inline]
+	fn cmp(&self,other:&Self)->core::cmp::Ordering{
+		self.as_str().cmp(other.as_str())
+	}
+}
+// Same as HashStr, so it must be used with IdentityHasher
+impl Hash for SmallHashStr<'_>{
+	#[inline]
+	fn hash<H:Hasher>(&self,state:&mut H){
+		state.write_u64(self.precomputed_hash());
+	}
+}
+impl GetHash for SmallHashStr<'_>{
+	#[inline]
+	fn get_hash(&self)->u64{
+		self.precomputed_hash()
+	}
+}
+impl GetHash for &SmallHashStr<'_>{
+	#[inline]
+	fn get_hash(&self)->u64{
+		self.precomputed_hash()
+	}
+}
+
+/// A standard `HashMap` using `SmallHashStr` as the key type with a custom `Hasher`
+/// that just uses the precomputed hash for speed instead of calculating it.
+#[cfg(feature="std")]
+pub type SmallHashStrMap<'a,V>=HashMap<SmallHashStr<'a>,V,BuildHasherDefault<IdentityHasher>>;
+
+/// A standard `HashSet` using `SmallHashStr` as the key type with a custom `Hasher`
+/// that just uses the precomputed hash for speed instead of calculating it.
+#[cfg(feature="std")]
+pub type SmallHashStrSet<'a>=HashSet<SmallHashStr<'a>,BuildHasherDefault<IdentityHasher>>;
+
+#[cfg(feature="cache")]
+impl<'host> crate::cache::HashStrCache<'host>{
+	/// Store the string inline if it is short enough,
+	/// otherwise intern it like `intern_with`.
+	#[inline]
+	pub fn intern_small_with(&mut self,host:&'host crate::cache::HashStrHost,index:impl GetHash+AsRef<str>)->SmallHashStr<'host>{
+		let (hash,str)=(index.get_hash(),index.as_ref());
+		SmallHashStr::new_with(HashedStr{hash,str},|HashedStr{hash,str}|self.intern_str_with_hash(||host.alloc_str_with_hash(hash,str),hash,str))
+	}
+}
+
+#[cfg(feature="global")]
+impl<'host> crate::global::Bins<'host>{
+	/// Store the string inline if it is short enough,
+	/// otherwise intern it into the global cache.
+	#[inline]
+	pub fn intern_small(&self,index:impl GetHash+AsRef<str>)->SmallHashStr<'host>{
+		let (hash,str)=(index.get_hash(),index.as_ref());
+		SmallHashStr::new_with(HashedStr{hash,str},|HashedStr{hash,str}|self.intern_str_with_hash(hash,str))
+	}
+}
+
+#[cfg(feature="std")]
+#[test]
+fn test_small(){
+	assert_eq!(core::mem::size_of::<SmallHashStr>(),32);
+
+	let short=SmallHashStr::try_inline("short").unwrap();
+	assert!(short.is_inline());
+	assert_eq!(short.as_str(),"short");
+	assert_eq!(short.precomputed_hash(),crate::hash::make_hash("short"));
+	assert!(SmallHashStr::try_inline("this string is too long to be inline").is_none());
+
+	let long=HashStr::anonymous("this string is too long to be inline".to_owned());
+	let long_small=SmallHashStr::from(&*long);
+	assert!(!long_small.is_inline());
+	assert_eq!(long_small.precomputed_hash(),long.precomputed_hash());
+
+	// inline and interned strings are equal when the contents are equal
+	let short_boxed=HashStr::anonymous("short".to_owned());
+	assert_eq!(short,SmallHashStr::from(&*short_boxed));
+
+	let mut map=SmallHashStrMap::default();
+	map.insert(short,1);
+	map.insert(long_small,2);
+	assert_eq!(map.get(&SmallHashStr::try_inline("short").unwrap()),Some(&1));
+	assert_eq!(map.get(&SmallHashStr::from(&*long)),Some(&2));
+}
@@ -0,0 +1,430 @@
+use crate::hash::HashTarget;
+use crate::hash_str::{HashStr,SIZE_HASH};
+use crate::ornaments::GetHash;
+use alloc::vec::Vec;
+
+// Persistent string table file format.  All integers and hashes are in the
+// endianness of the writer, detected with the endian marker.  The format is
+// native-endian only: HashStrs are lent straight out of the bytes, so a table
+// written with a different endianness or hash is rejected, not byte-swapped.
+// Encode the table again on the target instead.
+//
+// header:
+//   magic       [u8;8]  b"HASHSTR\0"
+//   endian      u32     0x01020304
+//   version     u32     1
+//   hash        u32     1 for the wide folded multiply, 2 for the narrow one
+//   reserved    u32     0
+//   len         u64     number of strings
+//   slot_count  u64     number of hash index slots, a power of two
+//   file_len    u64     total size of the file
+// records:      [(offset:u64,len:u64);len]  position of each HashStr in the file
+// slots:        [u32;slot_count]  open addressing hash index into the records
+// strings:      HashStrs, each aligned to the hash and padded to the alignment
+
+const MAGIC:[u8;8]=*b"HASHSTR\0";
+const ENDIAN:u32=0x0102_0304;
+const VERSION:u32=1;
+const HASH:u32=HashTarget::NATIVE.hash_id();
+const HEADER_SIZE:usize=48;
+const RECORD_SIZE:usize=16;
+const SLOT_SIZE:usize=4;
+const EMPTY:u32=u32::MAX;
+
+#[derive(Debug)]
+pub enum TableError{
+	TooShort,
+	/// The bytes are not aligned to the hash.
+	Unaligned,
+	Magic,
+	/// The table was written on a target with different endianness.
+	/// Tables are native-endian only and are not byte-swapped on load.
+	Endianness,
+	Version(u32),
+	/// The table was written with a hash which does not match this target.
+	Hash(u32),
+	Corrupt,
+	UTF8(core::str::Utf8Error),
+}
+impl core::fmt::Display for TableError{
+	fn fmt(&self,f:&mut core::fmt::Formatter<'_>)->core::fmt::Result{
+		write!(f,"{self:?}")
+	}
+}
+#[cfg(feature="std")]
+impl std::error::Error for TableError{}
+
+#[inline]
+fn read_u32(bytes:&[u8],at:usize)->u32{
+	u32::from_ne_bytes(bytes[at..at+4].try_into().unwrap())
+}
+#[inline]
+fn read_u64(bytes:&[u8],at:usize)->u64{
+	u64::from_ne_bytes(bytes[at..at+8].try_into().unwrap())
+}
+#[inline]
+fn read_usize(bytes:&[u8],at:usize)->Result<usize,TableError>{
+	usize::try_from(read_u64(bytes,at)).map_err(|_|TableError::Corrupt)
+}
+
+#[inline]
+fn slot_count(len:usize)->usize{
+	// keep the index at most half full so probing is short
+	(len*2).next_power_of_two()
+}
+
+/// A read-only string table borrowing from a byte buffer, such as a memory
+/// mapped file.  Every HashStr points straight into the buffer.  Tables are
+/// native-endian, and can only be read on a target with the same endianness
+/// and hash as the writer.
+///
+/// ```rust,no_run
+/// use hash_str::{hstr,HashStrTable};
+///
+/// # #[cfg(not(feature="std"))]
+/// # fn main(){}
+/// # #[cfg(feature="std")]
+/// # fn main()->Result<(),Box<dyn std::error::Error>>{
+/// std::fs::write("strings.table",HashStrTable::encode([hstr!("bruh")]))?;
+///
+/// // later, read the file into a buffer aligned to the hash
+/// // such as a memory map, which is page aligned, or u64 words
+/// let file=std::fs::read("strings.table")?;
+/// let mut words=vec![0u64;file.len().div_ceil(8)];
+/// for (word,chunk) in words.iter_mut().zip(file.chunks(8)){
+///     let mut buf=[0;8];
+///     buf[..chunk.len()].copy_from_slice(chunk);
+///     *word=u64::from_ne_bytes(buf);
+/// }
+/// // SAFETY: the words are initialized and cover file.len() bytes
+/// let bytes=unsafe{core::slice::from_raw_parts(words.as_ptr().cast::<u8>(),file.len())};
+/// let table=HashStrTable::from_bytes(bytes)?;
+/// assert!(table.get("bruh").is_some());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug,Clone,Copy)]
+pub struct HashStrTable<'a>{
+	bytes:&'a [u8],
+	len:usize,
+	slot_count:usize,
+}
+
+impl<'a> HashStrTable<'a>{
+	/// Encode HashStrs into the table format.  Duplicate strings are written once.
+	/// The table can only be read on a target with the same endianness and hash.
+	pub fn encode<'b>(iter:impl IntoIterator<Item=&'b HashStr>)->Vec<u8>{
+		let items:Vec<&HashStr>=iter.into_iter().collect();
+		let slot_count=slot_count(items.len());
+		let mask=slot_count-1;
+		let mut slots=alloc::vec![EMPTY;slot_count];
+		let mut hash_strs=Vec::with_capacity(items.len());
+		for hash_str in items{
+			let mut slot=hash_str.precomputed_hash() as usize&mask;
+			loop{
+				match slots[slot]{
+					EMPTY=>{
+						slots[slot]=hash_strs.len() as u32;
+						hash_strs.push(hash_str);
+						break;
+					},
+					i if hash_strs[i as usize]==hash_str=>break,
+					_=>slot=(slot+1)&mask,
+				}
+			}
+		}
+		assert!(hash_strs.len()<EMPTY as usize,"too many strings");
+
+		let strings_start=(HEADER_SIZE+RECORD_SIZE*hash_strs.len()+SLOT_SIZE*slot_count).next_multiple_of(SIZE_HASH);
+		let mut records=Vec::with_capacity(hash_strs.len());
+		let mut file_len=strings_start;
+		for hash_str in &hash_strs{
+			records.push((file_len,hash_str.as_str().len()));
+			file_len+=hash_str.as_hash_str_bytes().len().next_multiple_of(SIZE_HASH);
+		}
+
+		let mut bytes=Vec::with_capacity(file_len);
+		bytes.extend_from_slice(&MAGIC);
+		bytes.extend_from_slice(&ENDIAN.to_ne_bytes());
+		bytes.extend_from_slice(&VERSION.to_ne_bytes());
+		bytes.extend_from_slice(&HASH.to_ne_bytes());
+		bytes.extend_from_slice(&0u32.to_ne_bytes());
+		bytes.extend_from_slice(&(hash_strs.len() as u64).to_ne_bytes());
+		bytes.extend_from_slice(&(slot_count as u64).to_ne_bytes());
+		bytes.extend_from_slice(&(file_len as u64).to_ne_bytes());
+		for (offset,len) in records{
+			bytes.extend_from_slice(&(offset as u64).to_ne_bytes());
+			bytes.extend_from_slice(&(len as u64).to_ne_bytes());
+		}
+		for slot in slots{
+			bytes.extend_from_slice(&slot.to_ne_bytes());
+		}
+		bytes.resize(strings_start,0);
+		for hash_str in hash_strs{
+			bytes.extend_from_slice(hash_str.as_hash_str_bytes());
+			bytes.resize(bytes.len().next_multiple_of(SIZE_HASH),0);
+		}
+		debug_assert_eq!(bytes.len(),file_len);
+		bytes
+	}
+	/// Read a table written by `encode`, validating the whole table.
+	/// The bytes must be aligned to the hash.
+	pub fn from_bytes(bytes:&'a [u8])->Result<Self,TableError>{
+		if bytes.len()<HEADER_SIZE{
+			return Err(TableError::TooShort);
+		}
+		if bytes.as_ptr().align_offset(SIZE_HASH)!=0{
+			return Err(TableError::Unaligned);
+		}
+		if bytes[..8]!=MAGIC{
+			return Err(TableError::Magic);
+		}
+		// the format is native-endian only, HashStrs are not byte-swapped
+		if read_u32(bytes,8)!=ENDIAN{
+			return Err(TableError::Endianness);
+		}
+		match read_u32(bytes,12){
+			VERSION=>(),
+			version=>return Err(TableError::Version(version)),
+		}
+		match read_u32(bytes,16){
+			HASH=>(),
+			hash=>return Err(TableError::Hash(hash)),
+		}
+		let len=read_usize(bytes,24)?;
+		let slot_count=read_usize(bytes,32)?;
+		let file_len=read_usize(bytes,40)?;
+		if bytes.len()<file_len{
+			return Err(TableError::TooShort);
+		}
+		let bytes=&bytes[..file_len];
+		if !slot_count.is_power_of_two()||EMPTY as usize<=len{
+			return Err(TableError::Corrupt);
+		}
+		let strings_start=len.checked_mul(RECORD_SIZE)
+			.zip(slot_count.checked_mul(SLOT_SIZE))
+			.and_then(|(records,slots)|HEADER_SIZE.checked_add(records)?.checked_add(slots))
+			.and_then(|end|end.checked_next_multiple_of(SIZE_HASH))
+			.filter(|&start|start<=file_len)
+			.ok_or(TableError::Corrupt)?;
+		let table=Self{bytes,len,slot_count};
+		for i in 0..len{
+			let (offset,str_len)=table.record(i)?;
+			let end=offset.checked_add(SIZE_HASH)
+				.and_then(|start|start.checked_add(str_len))
+				.ok_or(TableError::Corrupt)?;
+			if offset<strings_start||offset%SIZE_HASH!=0||file_len<end{
+				return Err(TableError::Corrupt);
+			}
+			core::str::from_utf8(&bytes[offset+SIZE_HASH..end]).map_err(TableError::UTF8)?;
+		}
+		let mut has_empty=false;
+		for slot in 0..slot_count{
+			match table.slot(slot){
+				EMPTY=>has_empty=true,
+				i if len<=i as usize=>return Err(TableError::Corrupt),
+				_=>(),
+			}
+		}
+		// lookups stop at an empty slot
+		if !has_empty{
+			return Err(TableError::Corrupt);
+		}
+		Ok(table)
+	}
+	#[inline]
+	fn record(&self,i:usize)->Result<(usize,usize),TableError>{
+		let at=HEADER_SIZE+RECORD_SIZE*i;
+		Ok((read_usize(self.bytes,at)?,read_usize(self.bytes,at+8)?))
+	}
+	#[inline]
+	fn slot(&self,slot:usize)->u32{
+		read_u32(self.bytes,HEADER_SIZE+RECORD_SIZE*self.len+SLOT_SIZE*slot)
+	}
+	/// Get a HashStr by its position in the table.
+	#[inline]
+	pub fn get_index(&self,i:usize)->Option<&'a HashStr>{
+		if self.len<=i{
+			return None;
+		}
+		// the records were validated when the table was read
+		let (offset,len)=self.record(i).ok()?;
+		// SAFETY: the bytes are aligned, each HashStr is aligned, and the str is valid utf8
+		Some(unsafe{HashStr::ref_from_bytes_unchecked(&self.bytes[offset..offset+SIZE_HASH+len])})
+	}
+	#[inline]
+	pub(crate) fn get_str_with_hash(&self,hash:u64,str:&str)->Option<&'a HashStr>{
+		let mask=self.slot_count-1;
+		let mut slot=hash as usize&mask;
+		loop{
+			let i=self.slot(slot);
+			if i==EMPTY{
+				return None;
+			}
+			let hash_str=self.get_index(i as usize)?;
+			if hash_str.precomputed_hash()==hash&&hash_str.as_str()==str{
+				return Some(hash_str);
+			}
+			slot=(slot+1)&mask;
+		}
+	}
+	/// Fetch an existing HashStr, utilizing the precalculated hash if possible.
+	#[inline]
+	pub fn get(&self,index:impl GetHash+AsRef<str>)->Option<&'a HashStr>{
+		self.get_str_with_hash(index.get_hash(),index.as_ref())
+	}
+	#[inline]
+	pub fn contains(&self,index:impl GetHash+AsRef<str>)->bool{
+		self.get(index).is_some()
+	}
+	/// Finds an existing HashStr if it is present.  Can be chained
+	/// like `HashStrCache::presence` to spill missing items into a cache.
+	#[cfg(feature="cache")]
+	#[inline]
+	pub fn presence<'b>(&self,index:impl GetHash+Into<&'b str>)->crate::cache::Presence<&'a HashStr,crate::ornaments::HashedStr<'b>>{
+		use crate::cache::Presence;
+		let (hash,str)=(index.get_hash(),index.into());
+		match self.get_str_with_hash(hash,str){
+			Some(hash_str)=>Presence::Present(hash_str),
+			None=>Presence::Absent(crate::ornaments::HashedStr{hash,str}),
+		}
+	}
+	/// HashStrs in the order they were written.
+	#[inline]
+	pub fn iter(&self)->impl ExactSizeIterator<Item=&'a HashStr>+'_{
+		(0..self.len).map(|i|self.get_index(i).unwrap())
+	}
+	#[inline]
+	pub fn len(&self)->usize{
+		self.len
+	}
+	#[inline]
+	pub fn is_empty(&self)->bool{
+		self.len==0
+	}
+	/// The bytes of the whole table.
+	#[inline]
+	pub fn as_bytes(&self)->&'a [u8]{
+		self.bytes
+	}
+}
+
+#[cfg(feature="cache")]
+impl crate::cache::HashStrCache<'_>{
+	/// Encode the cached HashStrs into the `HashStrTable` format.
+	#[inline]
+	pub fn encode_table(&self)->Vec<u8>{
+		HashStrTable::encode(self.iter())
+	}
+}
+
+#[cfg(feature="global")]
+impl crate::global::Bins<'_>{
+	/// Encode the HashStrs in the global cache into the `HashStrTable` format.
+	/// Each bin is locked while it is read.
+	pub fn encode_table(&self)->Vec<u8>{
+		let mut hash_strs=Vec::new();
+		for bin in 0..crate::global::NUM_BINS{
+			hash_strs.extend(self.lock_bin(bin).cache.iter());
+		}
+		HashStrTable::encode(hash_strs)
+	}
+}
+
+#[cfg(test)]
+fn aligned(bytes:&[u8])->Vec<u64>{
+	let mut words=alloc::vec![0u64;bytes.len().div_ceil(SIZE_HASH)];
+	for (word,chunk) in words.iter_mut().zip(bytes.chunks(SIZE_HASH)){
+		let mut buf=[0;SIZE_HASH];
+		buf[..chunk.len()].copy_from_slice(chunk);
+		*word=u64::from_ne_bytes(buf);
+	}
+	words
+}
+#[cfg(test)]
+fn as_bytes(words:&[u64],len:usize)->&[u8]{
+	// SAFETY: any u64 is valid bytes
+	unsafe{core::slice::from_raw_parts(words.as_ptr() as *const u8,len)}
+}
+
+#[test]
+fn test_table(){
+	use crate::hstr;
+	let words=["","a","table","persistent string table","a","bruh"];
+	let hash_strs:Vec<_>=words.iter().map(|&word|HashStr::anonymous(word.to_owned())).collect();
+	let bytes=HashStrTable::encode(hash_strs.iter().map(|hash_str|&**hash_str));
+	let words_aligned=aligned(&bytes);
+	let table=HashStrTable::from_bytes(as_bytes(&words_aligned,bytes.len())).unwrap();
+	assert_eq!(table.len(),5);
+	assert!(table.iter().eq(["","a","table","persistent string table","bruh"]));
+	for word in words{
+		let hash_str=table.get(word).unwrap();
+		assert_eq!(hash_str,&*HashStr::anonymous(word.to_owned()));
+		assert!(core::ptr::addr_eq(hash_str,table.get(hash_str).unwrap()));
+		assert_eq!(hash_str.as_hash_str_bytes().as_ptr().align_offset(SIZE_HASH),0);
+	}
+	assert_eq!(table.get("missing"),None);
+	assert_eq!(table.get(hstr!("bruh")).unwrap().as_str(),"bruh");
+
+	let empty=HashStrTable::encode([]);
+	let empty_aligned=aligned(&empty);
+	let empty=HashStrTable::from_bytes(as_bytes(&empty_aligned,empty.len())).unwrap();
+	assert!(empty.is_empty());
+	assert_eq!(empty.get("a"),None);
+
+	// errors
+	assert!(matches!(HashStrTable::from_bytes(as_bytes(&words_aligned,bytes.len()-1)),Err(TableError::TooShort)));
+	let mut corrupt=bytes.clone();
+	corrupt[0]=b'X';
+	assert!(matches!(HashStrTable::from_bytes(as_bytes(&aligned(&corrupt),corrupt.len())),Err(TableError::Magic)));
+	// a table written with the other endianness is rejected rather than byte-swapped
+	let mut foreign=bytes.clone();
+	foreign[8..12].reverse();
+	assert!(matches!(HashStrTable::from_bytes(as_bytes(&aligned(&foreign),foreign.len())),Err(TableError::Endianness)));
+	let mut corrupt=bytes.clone();
+	corrupt[24..32].copy_from_slice(&u64::MAX.to_ne_bytes());
+	assert!(matches!(HashStrTable::from_bytes(as_bytes(&aligned(&corrupt),corrupt.len())),Err(TableError::Corrupt)));
+	let mut corrupt=bytes.clone();
+	// the first byte of "bruh"
+	let last=corrupt.len()-SIZE_HASH;
+	corrupt[last]=0xff;
+	assert!(matches!(HashStrTable::from_bytes(as_bytes(&aligned(&corrupt),corrupt.len())),Err(TableError::UTF8(_))));
+	let mut shifted=alloc::vec![0];
+	shifted.extend_from_slice(&bytes);
+	let shifted_aligned=aligned(&shifted);
+	assert!(matches!(HashStrTable::from_bytes(&as_bytes(&shifted_aligned,shifted.len())[1..]),Err(TableError::Unaligned)));
+}
+
+#[cfg(feature="cache")]
+#[test]
+fn test_cache_table(){
+	use crate::cache::{HashStrHost,HashStrCache};
+	let host=HashStrHost::new();
+	let mut cache=HashStrCache::new();
+	for i in 0..100{
+		cache.intern_with(&host,alloc::format!("cache_table_{i}").as_str());
+	}
+	let bytes=cache.encode_table();
+	let words=aligned(&bytes);
+	let table=HashStrTable::from_bytes(as_bytes(&words,bytes.len())).unwrap();
+	assert_eq!(table.len(),100);
+	for hash_str in cache.iter(){
+		assert_eq!(table.get(hash_str),Some(hash_str));
+	}
+	// chain a missing string into a cache
+	let mut spill=HashStrCache::new();
+	let hs=table.presence("cache_table_100").or_intern_with(&host,&mut spill);
+	assert_eq!(hs.as_str(),"cache_table_100");
+	assert_eq!(spill.len(),1);
+}
+
+#[cfg(feature="global")]
+#[test]
+fn test_global_table(){
+	let cache=crate::global::get_cache();
+	let hs=cache.intern("global_table");
+	let bytes=cache.encode_table();
+	let words=aligned(&bytes);
+	let table=HashStrTable::from_bytes(as_bytes(&words,bytes.len())).unwrap();
+	assert_eq!(table.get(hs),Some(hs));
+}
@@ -0,0 +1,31 @@
+#![cfg(feature="derive")]
+use hash_str::{hstr,HashStr,HashStrEnum,UnknownVariantError};
+
+#[derive(Debug,Clone,Copy,PartialEq,HashStrEnum)]
+#[hash_str(rename_all="snake_case")]
+enum Method{
+	Get,
+	PostForm,
+	#[hash_str(rename="DELETE")]
+	Delete,
+}
+
+#[test]
+fn derive_hash_str_enum(){
+	assert_eq!(Method::Get.as_hash_str(),hstr!("get"));
+	assert_eq!(Method::PostForm.as_hash_str(),hstr!("post_form"));
+	assert_eq!(Method::Delete.as_hash_str(),hstr!("DELETE"));
+	assert_eq!(Method::try_from(hstr!("post_form")),Ok(Method::PostForm));
+	assert_eq!(Method::try_from(&*HashStr::anonymous("DELETE".to_owned())),Ok(Method::Delete));
+	assert_eq!(Method::try_from(hstr!("Get")),Err(UnknownVariantError));
+}
+
+#[test]
+fn derive_round_trip(){
+	for variant in [Method::Get,Method::PostForm,Method::Delete]{
+		let hash_str=variant.as_hash_str();
+		assert_eq!(Method::try_from(hash_str),Ok(variant));
+		// an equal HashStr from a different allocation, hashed at runtime
+		assert_eq!(Method::try_from(&*HashStr::anonymous(hash_str.as_str().to_owned())),Ok(variant));
+	}
+}